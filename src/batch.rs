@@ -0,0 +1,530 @@
+//! Checking many points at once from Rust, without going through numpy.
+//!
+//! The queries are implemented for `RoaringMask`, `Shapes` and `RoaringLandmask`. Points are
+//! given as separate slices of longitudes and latitudes, as slices of pairs, as iterators or as
+//! `ndarray` views. The results are written into a buffer given by the caller, so that the same
+//! buffer can be reused between calls. Every query has a parallel version running on rayon.
+//!
+//! Points with NaN or infinite coordinates, or latitudes outside [-90, 90], can be handled with
+//! an `InvalidPolicy`, or marked with `-1` in an `i8` result.
+use ndarray::{ArrayView, ArrayView1, ArrayViewMut, ArrayViewMut1, Dimension, Zip};
+#[cfg(feature = "python")]
+use ndarray::{ArrayViewD, ArrayViewMutD, IxDyn};
+#[cfg(feature = "python")]
+use numpy::{PyArray, PyArrayDyn, PyReadonlyArrayDyn, PyReadwriteArrayDyn};
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::error::{InvalidPolicy, LandmaskError};
+use crate::{RoaringLandmask, RoaringMask, Shapes};
+
+/// Check whether many points are on land.
+///
+/// `x` is longitude, [-180, 180] east
+/// `y` is latitude,  [- 90,  90] north
+///
+/// The batch queries panic if the output and the coordinates do not have the same length or
+/// shape, or if a point is outside the range checked by `contains_point`.
+pub trait ContainsMany {
+    /// Check whether the point (x, y) is on land, this is the `contains` of the type.
+    fn contains_point(&self, x: f64, y: f64) -> bool;
+
+    /// Check whether the point (x, y) is on land, this is the `try_contains` of the type.
+    fn try_contains_point(&self, x: f64, y: f64) -> Result<bool, LandmaskError>;
+
+    /// Check the points as they come from the iterator.
+    fn contains_iter<'a, I>(&'a self, points: I) -> Box<dyn Iterator<Item = bool> + 'a>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+        I::IntoIter: 'a,
+    {
+        Box::new(
+            points
+                .into_iter()
+                .map(move |(x, y)| self.contains_point(x, y)),
+        )
+    }
+
+    /// Check the points in `x` and `y`, writing the results to `out`.
+    fn contains_slice(&self, x: &[f64], y: &[f64], out: &mut [bool]) {
+        self.contains_array(
+            ArrayView1::from(x),
+            ArrayView1::from(y),
+            ArrayViewMut1::from(out),
+        );
+    }
+
+    /// Check the points in `x` and `y` in parallel, writing the results to `out`.
+    fn contains_slice_par(&self, x: &[f64], y: &[f64], out: &mut [bool])
+    where
+        Self: Sync,
+    {
+        self.contains_array_par(
+            ArrayView1::from(x),
+            ArrayView1::from(y),
+            ArrayViewMut1::from(out),
+        );
+    }
+
+    /// Check the (x, y) pairs in `points`, writing the results to `out`.
+    fn contains_pairs(&self, points: &[(f64, f64)], out: &mut [bool]) {
+        Zip::from(ArrayViewMut1::from(out))
+            .and(ArrayView1::from(points))
+            .for_each(|o, &(x, y)| *o = self.contains_point(x, y));
+    }
+
+    /// Check the (x, y) pairs in `points` in parallel, writing the results to `out`.
+    fn contains_pairs_par(&self, points: &[(f64, f64)], out: &mut [bool])
+    where
+        Self: Sync,
+    {
+        Zip::from(ArrayViewMut1::from(out))
+            .and(ArrayView1::from(points))
+            .par_for_each(|o, &(x, y)| *o = self.contains_point(x, y));
+    }
+
+    /// Check the points in the arrays `x` and `y`, writing the results to `out`.
+    fn contains_array<D: Dimension>(
+        &self,
+        x: ArrayView<f64, D>,
+        y: ArrayView<f64, D>,
+        out: ArrayViewMut<bool, D>,
+    ) {
+        Zip::from(out)
+            .and(x)
+            .and(y)
+            .for_each(|o, &x, &y| *o = self.contains_point(x, y));
+    }
+
+    /// Check the points in the arrays `x` and `y` in parallel, writing the results to `out`.
+    fn contains_array_par<D: Dimension>(
+        &self,
+        x: ArrayView<f64, D>,
+        y: ArrayView<f64, D>,
+        out: ArrayViewMut<bool, D>,
+    ) where
+        Self: Sync,
+    {
+        Zip::from(out)
+            .and(x)
+            .and(y)
+            .par_for_each(|o, &x, &y| *o = self.contains_point(x, y));
+    }
+
+    /// Check the points in the arrays `x` and `y`, writing the results to `out`. Invalid points
+    /// are handled according to `invalid`.
+    fn contains_array_with<D: Dimension>(
+        &self,
+        x: ArrayView<f64, D>,
+        y: ArrayView<f64, D>,
+        out: ArrayViewMut<bool, D>,
+        invalid: InvalidPolicy,
+    ) -> Result<(), LandmaskError> {
+        invalid.check(x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)))?;
+
+        Zip::from(out)
+            .and(x)
+            .and(y)
+            .for_each(|o, &x, &y| *o = invalid.resolve(self.try_contains_point(x, y)));
+
+        Ok(())
+    }
+
+    /// Check the points in the arrays `x` and `y` in parallel, writing the results to `out`.
+    /// Invalid points are handled according to `invalid`.
+    fn contains_array_with_par<D: Dimension>(
+        &self,
+        x: ArrayView<f64, D>,
+        y: ArrayView<f64, D>,
+        out: ArrayViewMut<bool, D>,
+        invalid: InvalidPolicy,
+    ) -> Result<(), LandmaskError>
+    where
+        Self: Sync,
+    {
+        invalid.check(x.iter().zip(y.iter()).map(|(x, y)| (*x, *y)))?;
+
+        Zip::from(out)
+            .and(x)
+            .and(y)
+            .par_for_each(|o, &x, &y| *o = invalid.resolve(self.try_contains_point(x, y)));
+
+        Ok(())
+    }
+
+    /// Check the points in the arrays `x` and `y`, writing `1` for land, `0` for ocean and `-1`
+    /// for invalid points to `out`.
+    fn contains_array_i8<D: Dimension>(
+        &self,
+        x: ArrayView<f64, D>,
+        y: ArrayView<f64, D>,
+        out: ArrayViewMut<i8, D>,
+    ) {
+        Zip::from(out)
+            .and(x)
+            .and(y)
+            .for_each(|o, &x, &y| *o = contains_i8(self.try_contains_point(x, y)));
+    }
+
+    /// Check the points in the arrays `x` and `y` in parallel, writing `1` for land, `0` for
+    /// ocean and `-1` for invalid points to `out`.
+    fn contains_array_i8_par<D: Dimension>(
+        &self,
+        x: ArrayView<f64, D>,
+        y: ArrayView<f64, D>,
+        out: ArrayViewMut<i8, D>,
+    ) where
+        Self: Sync,
+    {
+        Zip::from(out)
+            .and(x)
+            .and(y)
+            .par_for_each(|o, &x, &y| *o = contains_i8(self.try_contains_point(x, y)));
+    }
+}
+
+fn contains_i8(contains: Result<bool, LandmaskError>) -> i8 {
+    match contains {
+        Ok(c) => c as i8,
+        Err(_) => -1,
+    }
+}
+
+/// A coordinate in a numpy array. Integers are scaled to degrees by the caller.
+#[cfg(feature = "python")]
+trait Coordinate: numpy::Element + Copy + Sync {
+    fn to_f64(self) -> f64;
+}
+
+#[cfg(feature = "python")]
+macro_rules! impl_coordinate {
+    ($($t:ty),*) => {
+        $(
+            impl Coordinate for $t {
+                fn to_f64(self) -> f64 {
+                    self as f64
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "python")]
+impl_coordinate!(f64, f32, i64, i32);
+
+/// Coordinates from numpy, as float64, float32, int64 or int32. The coordinates are used as they
+/// are, without making a float64 copy of the array.
+#[cfg(feature = "python")]
+#[derive(FromPyObject)]
+pub enum PyCoords<'py> {
+    F64(PyReadonlyArrayDyn<'py, f64>),
+    F32(PyReadonlyArrayDyn<'py, f32>),
+    I64(PyReadonlyArrayDyn<'py, i64>),
+    I32(PyReadonlyArrayDyn<'py, i32>),
+}
+
+/// Evaluate `$body` with `$a` and `$b` bound to the array views of `$x` and `$y`, which must
+/// have the same type.
+#[cfg(feature = "python")]
+macro_rules! with_coords {
+    ($x:expr, $y:expr, |$a:ident, $b:ident| $body:expr) => {
+        match ($x, $y) {
+            (PyCoords::F64(x), PyCoords::F64(y)) => {
+                let ($a, $b) = (x.as_array(), y.as_array());
+                $body
+            }
+            (PyCoords::F32(x), PyCoords::F32(y)) => {
+                let ($a, $b) = (x.as_array(), y.as_array());
+                $body
+            }
+            (PyCoords::I64(x), PyCoords::I64(y)) => {
+                let ($a, $b) = (x.as_array(), y.as_array());
+                $body
+            }
+            (PyCoords::I32(x), PyCoords::I32(y)) => {
+                let ($a, $b) = (x.as_array(), y.as_array());
+                $body
+            }
+            _ => Err(LandmaskError::InvalidInput(
+                "x and y must have the same dtype".into(),
+            )),
+        }
+    };
+}
+
+/// The Python `contains_many`, shared by the types. The coordinates are multiplied by `scale`,
+/// e.g. `1e-7` for integer coordinates in units of 1e-7 degrees. The results are written to `out`
+/// if it is given, in the order of the points, otherwise to a new one-dimensional array. The GIL
+/// is released while checking the points.
+#[cfg(feature = "python")]
+pub(crate) fn contains_many_py<M: ContainsMany + Sync>(
+    mask: &M,
+    py: Python,
+    x: PyCoords,
+    y: PyCoords,
+    out: Option<&PyArrayDyn<bool>>,
+    scale: f64,
+    invalid: InvalidPolicy,
+) -> Result<PyObject, LandmaskError> {
+    fn run<M: ContainsMany + Sync, T: Coordinate>(
+        mask: &M,
+        py: Python,
+        x: ArrayViewD<T>,
+        y: ArrayViewD<T>,
+        out: Option<&PyArrayDyn<bool>>,
+        scale: f64,
+        invalid: InvalidPolicy,
+    ) -> Result<PyObject, LandmaskError> {
+        check_len(x.len(), y.len(), out.map(|o| o.len()))?;
+
+        let fill = |mut out: ArrayViewMutD<bool>| -> Result<(), LandmaskError> {
+            let points = || {
+                x.iter()
+                    .zip(y.iter())
+                    .map(|(x, y)| (x.to_f64() * scale, y.to_f64() * scale))
+            };
+
+            invalid.check(points())?;
+
+            for (o, (x, y)) in out.iter_mut().zip(points()) {
+                *o = invalid.resolve(mask.try_contains_point(x, y));
+            }
+
+            Ok(())
+        };
+
+        match out {
+            Some(out) => {
+                let mut o = readwrite(out)?;
+                let o = o.as_array_mut();
+                py.allow_threads(|| fill(o))?;
+
+                Ok(out.to_owned().into_py(py))
+            }
+            None => {
+                let mut contains = ndarray::ArrayD::from_elem(IxDyn(&[x.len()]), false);
+                py.allow_threads(|| fill(contains.view_mut()))?;
+
+                Ok(PyArray::from_owned_array(py, contains)
+                    .to_owned()
+                    .into_py(py))
+            }
+        }
+    }
+
+    with_coords!(x, y, |x, y| run(mask, py, x, y, out, scale, invalid))
+}
+
+/// The Python `contains_many_par`, shared by the types, see `contains_many_py`. The result has the
+/// same shape as `x` and `y`.
+#[cfg(feature = "python")]
+pub(crate) fn contains_many_par_py<M: ContainsMany + Sync>(
+    mask: &M,
+    py: Python,
+    x: PyCoords,
+    y: PyCoords,
+    out: Option<&PyArrayDyn<bool>>,
+    scale: f64,
+    invalid: InvalidPolicy,
+) -> Result<PyObject, LandmaskError> {
+    fn run<M: ContainsMany + Sync, T: Coordinate>(
+        mask: &M,
+        py: Python,
+        x: ArrayViewD<T>,
+        y: ArrayViewD<T>,
+        out: Option<&PyArrayDyn<bool>>,
+        scale: f64,
+        invalid: InvalidPolicy,
+    ) -> Result<PyObject, LandmaskError> {
+        check_shape(x.shape(), y.shape(), out.map(|o| o.shape()))?;
+
+        let fill = |out: ArrayViewMutD<bool>| -> Result<(), LandmaskError> {
+            invalid.check(
+                x.iter()
+                    .zip(y.iter())
+                    .map(|(x, y)| (x.to_f64() * scale, y.to_f64() * scale)),
+            )?;
+
+            Zip::from(out).and(&x).and(&y).par_for_each(|o, x, y| {
+                *o =
+                    invalid.resolve(mask.try_contains_point(x.to_f64() * scale, y.to_f64() * scale))
+            });
+
+            Ok(())
+        };
+
+        match out {
+            Some(out) => {
+                let mut o = readwrite(out)?;
+                let o = o.as_array_mut();
+                py.allow_threads(|| fill(o))?;
+
+                Ok(out.to_owned().into_py(py))
+            }
+            None => {
+                let mut contains = ndarray::Array::from_elem(x.raw_dim(), false);
+                py.allow_threads(|| fill(contains.view_mut()))?;
+
+                Ok(PyArray::from_owned_array(py, contains)
+                    .to_owned()
+                    .into_py(py))
+            }
+        }
+    }
+
+    with_coords!(x, y, |x, y| run(mask, py, x, y, out, scale, invalid))
+}
+
+/// The Python `contains_many_i8`, see `contains_many_par_py`.
+#[cfg(feature = "python")]
+pub(crate) fn contains_many_i8_py<M: ContainsMany + Sync>(
+    mask: &M,
+    py: Python,
+    x: PyCoords,
+    y: PyCoords,
+    scale: f64,
+) -> Result<Py<PyArray<i8, numpy::IxDyn>>, LandmaskError> {
+    fn run<M: ContainsMany + Sync, T: Coordinate>(
+        mask: &M,
+        py: Python,
+        x: ArrayViewD<T>,
+        y: ArrayViewD<T>,
+        scale: f64,
+    ) -> Result<Py<PyArray<i8, numpy::IxDyn>>, LandmaskError> {
+        check_shape(x.shape(), y.shape(), None)?;
+
+        let contains = py.allow_threads(|| {
+            Zip::from(&x).and(&y).map_collect(|x, y| {
+                contains_i8(mask.try_contains_point(x.to_f64() * scale, y.to_f64() * scale))
+            })
+        });
+
+        Ok(PyArray::from_owned_array(py, contains).to_owned())
+    }
+
+    with_coords!(x, y, |x, y| run(mask, py, x, y, scale))
+}
+
+/// Borrow the output array for writing, it must not be one of the coordinate arrays.
+#[cfg(feature = "python")]
+fn readwrite(out: &PyArrayDyn<bool>) -> Result<PyReadwriteArrayDyn<'_, bool>, LandmaskError> {
+    out.try_readwrite()
+        .map_err(|_| LandmaskError::InvalidInput("out is already borrowed".into()))
+}
+
+/// Check that the coordinates, and the output if given, have the same number of elements.
+#[cfg(feature = "python")]
+fn check_len(x: usize, y: usize, out: Option<usize>) -> Result<(), LandmaskError> {
+    if x != y || out.is_some_and(|o| o != x) {
+        Err(LandmaskError::InvalidInput(
+            "x, y and out must have the same size".into(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Check that the coordinates, and the output if given, have the same shape.
+#[cfg(feature = "python")]
+fn check_shape(x: &[usize], y: &[usize], out: Option<&[usize]>) -> Result<(), LandmaskError> {
+    if x != y || out.is_some_and(|o| o != x) {
+        Err(LandmaskError::InvalidInput(
+            "x, y and out must have the same shape".into(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+impl ContainsMany for RoaringMask {
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        self.contains(x, y)
+    }
+
+    fn try_contains_point(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        self.try_contains(x, y)
+    }
+}
+
+impl ContainsMany for Shapes {
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        self.contains(x, y)
+    }
+
+    fn try_contains_point(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        self.try_contains(x, y)
+    }
+}
+
+impl ContainsMany for RoaringLandmask {
+    fn contains_point(&self, x: f64, y: f64) -> bool {
+        self.contains(x, y)
+    }
+
+    fn try_contains_point(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        self.try_contains(x, y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::LandmaskProvider;
+    use ndarray::Array2;
+
+    fn check_all<M: ContainsMany + Sync>(mask: &M) {
+        let (x, y): (Vec<f64>, Vec<f64>) = (0..72)
+            .flat_map(|i| (0..36).map(move |j| (i as f64 * 5. - 180., j as f64 * 5. - 87.5)))
+            .unzip();
+        let points: Vec<(f64, f64)> = x.iter().copied().zip(y.iter().copied()).collect();
+
+        let expected: Vec<bool> = mask.contains_iter(points.iter().copied()).collect();
+        assert!(expected.iter().any(|c| *c));
+        assert!(expected.iter().any(|c| !*c));
+
+        let mut out = vec![false; x.len()];
+        mask.contains_slice(&x, &y, &mut out);
+        assert_eq!(out, expected);
+
+        let mut out = vec![false; x.len()];
+        mask.contains_slice_par(&x, &y, &mut out);
+        assert_eq!(out, expected);
+
+        let mut out = vec![false; x.len()];
+        mask.contains_pairs(&points, &mut out);
+        assert_eq!(out, expected);
+
+        let mut out = vec![false; x.len()];
+        mask.contains_pairs_par(&points, &mut out);
+        assert_eq!(out, expected);
+
+        let xa = Array2::from_shape_vec((72, 36), x).unwrap();
+        let ya = Array2::from_shape_vec((72, 36), y).unwrap();
+        let expected = Array2::from_shape_vec((72, 36), expected).unwrap();
+
+        let mut out = Array2::from_elem((72, 36), false);
+        mask.contains_array(xa.view(), ya.view(), out.view_mut());
+        assert_eq!(out, expected);
+
+        let mut out = Array2::from_elem((72, 36), false);
+        mask.contains_array_par(xa.view(), ya.view(), out.view_mut());
+        assert_eq!(out, expected);
+    }
+
+    #[test]
+    fn test_contains_many() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            check_all(&RoaringMask::new(provider).unwrap());
+            check_all(&Shapes::new(provider).unwrap());
+            check_all(&RoaringLandmask::new_with_provider(provider).unwrap());
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_length_mismatch() {
+        let mask = RoaringMask::new(LandmaskProvider::Gshhg).unwrap();
+        let mut out = vec![false; 2];
+        mask.contains_slice(&[0., 1.], &[0.], &mut out);
+    }
+}
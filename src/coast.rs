@@ -0,0 +1,1068 @@
+//! Coastline segments of the land shapes, indexed in tiles.
+//!
+//! The edges of all the rings in the shapes are bucketed into tiles of `TILE` degrees, so that
+//! queries that only need the coastline close to a point (e.g. the distance to the coast) only
+//! visit the edges in the tiles around the point, rather than the whole geometry. Tiles are
+//! grouped into blocks of 1 degree, which are used to quickly skip empty regions.
+//!
+//! Every tile also has a reference point which is known to be on land or not, so that a point can
+//! be checked against the land polygons by counting the crossings of the coastline between the
+//! point and the reference point of its tile, without visiting any other edges.
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::collections::HashMap;
+
+use crate::modulate_longitude;
+
+/// Mean radius of the Earth (m).
+pub const EARTH_RADIUS: f64 = 6_371_008.8;
+
+/// Size of tiles (degrees).
+const TILE: f64 = 0.125;
+const TNX: i64 = 2880;
+const TNY: i64 = 1440;
+
+/// Number of tiles in a block, along each axis.
+const BLOCK: i64 = 8;
+const BNX: i64 = TNX / BLOCK;
+const BNY: i64 = TNY / BLOCK;
+
+/// A ring starts at point `start` in `points`, and ends where the next ring starts.
+#[derive(Debug, Clone, Copy)]
+struct Ring {
+    start: u32,
+    land_on_left: bool,
+}
+
+/// An edge in a tile. The edge starts at point `edge` in `points`, and `wrap` is the number of
+/// turns (360 degrees) the edge has to be shifted to be within the tile.
+#[derive(Debug, Clone, Copy)]
+struct Entry {
+    edge: u32,
+    wrap: i8,
+}
+
+/// The coastline point closest to a point.
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Debug, Clone, Copy)]
+pub struct Nearest {
+    /// Longitude of nearest point on the coastline.
+    pub x: f64,
+    /// Latitude of nearest point on the coastline.
+    pub y: f64,
+    /// Distance from query point to the coastline (m).
+    pub distance: f64,
+    /// Initial bearing from query point to the coastline (degrees clockwise from north).
+    pub bearing: f64,
+    /// Eastward component of the unit normal of the coastline, pointing out of land.
+    pub normal_x: f64,
+    /// Northward component of the unit normal of the coastline, pointing out of land.
+    pub normal_y: f64,
+}
+
+#[derive(Clone)]
+pub struct Coastline {
+    points: Vec<[f64; 2]>,
+    rings: Vec<Ring>,
+    tiles: HashMap<i64, Vec<Entry>>,
+    blocks: Vec<bool>,
+    /// Bitset of the tiles with their reference point on land.
+    reference: Vec<u64>,
+}
+
+impl Coastline {
+    /// Build the coastline from the rings of the land polygons. Each ring must be closed, and is
+    /// given along with whether it is an exterior ring (`true`) or a hole in a polygon (`false`).
+    pub fn from_rings<I>(rings: I) -> Coastline
+    where
+        I: IntoIterator<Item = (Vec<[f64; 2]>, bool)>,
+    {
+        let mut points: Vec<[f64; 2]> = Vec::new();
+        let mut rs = Vec::new();
+        let mut tiles: HashMap<i64, Vec<Entry>> = HashMap::new();
+        let mut blocks = vec![false; (BNX * BNY) as usize];
+
+        for (ring, exterior) in rings {
+            if ring.len() < 2 {
+                continue;
+            }
+
+            let start = points.len() as u32;
+
+            // Land is inside exterior rings and outside holes.
+            rs.push(Ring {
+                start,
+                land_on_left: (signed_area(&ring) > 0.) == exterior,
+            });
+
+            for (i, e) in ring.windows(2).enumerate() {
+                let edge = start + i as u32;
+
+                segment_tiles(e[0], e[1], |tx, ty| {
+                    let w = tx.rem_euclid(TNX);
+                    let wrap = ((w - tx) / TNX) as i8;
+
+                    tiles
+                        .entry(ty * TNX + w)
+                        .or_default()
+                        .push(Entry { edge, wrap });
+                    blocks[((ty / BLOCK) * BNX + w / BLOCK) as usize] = true;
+                });
+            }
+
+            points.extend(ring);
+        }
+
+        let mut coast = Coastline {
+            points,
+            rings: rs,
+            tiles,
+            blocks,
+            reference: vec![0; (TNX * TNY) as usize / 64],
+        };
+
+        let lats = (0..TNY).map(|ty| reference_point(0, ty)[1]);
+        let mut reference = vec![0; coast.reference.len()];
+        let mut ty = 0;
+
+        coast.scan(lats, |_, xs| {
+            for tx in 0..TNX {
+                let x = reference_point(tx, ty)[0];
+                if xs.partition_point(|c| *c < x) % 2 == 1 {
+                    let t = (ty * TNX + tx) as usize;
+                    reference[t / 64] |= 1 << (t % 64);
+                }
+            }
+            ty += 1;
+        });
+
+        coast.reference = reference;
+        coast
+    }
+
+    /// Check if point (x, y) is on land.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// Only the edges in the tile of the point are visited: the point is on land if the
+    /// reference point of the tile is on land and the segment between them crosses the coastline
+    /// an even number of times, or the other way around. Points on the coastline are not on land,
+    /// while points on the seams where the polygons have been cut are.
+    ///
+    /// The rings must not cross the antimeridian.
+    pub fn contains(&self, x: f64, y: f64) -> bool {
+        let tx = (((x + 180.) / TILE).floor() as i64).clamp(0, TNX - 1);
+        let ty = tile_y(y);
+
+        let t = (ty * TNX + tx) as usize;
+        let r = reference_point(tx, ty);
+        let mut land = self.reference[t / 64] & (1 << (t % 64)) != 0;
+
+        for (e, a, b) in self.tile_edges(tx, ty) {
+            match crosses(r, [x, y], a, b) {
+                Some(true) => land = !land,
+                Some(false) => {}
+                None if self.is_seam(e) => {}
+                None => return false,
+            }
+        }
+
+        land
+    }
+
+    /// The rings of the coastline, along with whether the ring is an exterior ring, like they were
+    /// given to `from_rings`.
+    pub fn rings(&self) -> Vec<(Vec<[f64; 2]>, bool)> {
+        self.rings
+            .iter()
+            .zip(self.ring_ranges())
+            .map(|(r, (start, end))| {
+                let ring = self.points[start as usize..end as usize].to_vec();
+                let exterior = (signed_area(&ring) > 0.) == r.land_on_left;
+                (ring, exterior)
+            })
+            .collect()
+    }
+
+    /// Iterate over the edges of all the rings, along with whether the edge is a seam along the
+    /// antimeridian or the poles (see `nearest`).
+    pub fn edges(&self) -> impl Iterator<Item = ([f64; 2], [f64; 2], bool)> + '_ {
+        self.edge_ids().map(move |e| {
+            let a = self.points[e as usize];
+            let b = self.points[e as usize + 1];
+            (a, b, is_seam(a, b))
+        })
+    }
+
+    /// Call `f` with each latitude, which must be increasing, and the sorted longitudes where the
+    /// rings cross the parallel. Land is between the first and the second crossing, the third
+    /// and the fourth, and so on. An edge crosses the parallel if it starts on or below it and
+    /// ends above it, or the other way around, so horizontal edges never do.
+    ///
+    /// The rings must not cross the antimeridian.
+    pub fn scan<I, F>(&self, lats: I, mut f: F)
+    where
+        I: IntoIterator<Item = f64>,
+        F: FnMut(f64, &[f64]),
+    {
+        // Edges ordered by their lower end.
+        let lower = |e: u32| {
+            let a = self.points[e as usize];
+            let b = self.points[e as usize + 1];
+            if a[1] < b[1] {
+                (a, b)
+            } else {
+                (b, a)
+            }
+        };
+
+        let mut edges = self
+            .edge_ids()
+            .filter(|e| self.points[*e as usize][1] != self.points[*e as usize + 1][1])
+            .collect::<Vec<_>>();
+        edges.sort_by(|a, b| lower(*a).0[1].partial_cmp(&lower(*b).0[1]).unwrap());
+
+        let mut next = 0;
+        let mut active: Vec<([f64; 2], [f64; 2])> = Vec::new();
+        let mut xs = Vec::new();
+
+        for lat in lats {
+            while next < edges.len() && lower(edges[next]).0[1] <= lat {
+                active.push(lower(edges[next]));
+                next += 1;
+            }
+            active.retain(|(_, b)| b[1] > lat);
+
+            xs.clear();
+            xs.extend(
+                active
+                    .iter()
+                    .map(|(a, b)| a[0] + (b[0] - a[0]) * (lat - a[1]) / (b[1] - a[1])),
+            );
+            xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+            f(lat, &xs);
+        }
+    }
+
+    /// The ids of the edges of all the rings.
+    fn edge_ids(&self) -> impl Iterator<Item = u32> + '_ {
+        self.ring_ranges().flat_map(|(start, end)| start..end - 1)
+    }
+
+    /// The range of points of each ring.
+    fn ring_ranges(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        let ends = self
+            .rings
+            .iter()
+            .skip(1)
+            .map(|r| r.start)
+            .chain(std::iter::once(self.points.len() as u32));
+
+        self.rings.iter().map(|r| r.start).zip(ends)
+    }
+
+    /// Find the point on the coastline closest to point (x, y).
+    ///
+    /// `x` is longitude, east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// The nearest point is searched for using a local equirectangular approximation around the
+    /// query point, while the distance is the great-circle distance to the nearest point. The
+    /// artificial edges along the antimeridian and the poles, where the land polygons have been
+    /// cut, are not part of the coastline.
+    ///
+    /// The normal is the normal of the coastline edge the nearest point is on, in a local east,
+    /// north frame.
+    ///
+    /// Returns `None` if the coastline is empty.
+    pub fn nearest(&self, x: f64, y: f64) -> Option<Nearest> {
+        let c = y.to_radians().cos().max(0.01);
+
+        let tx0 = (((x + 180.) / TILE).floor()) as i64;
+        let ty0 = tile_y(y);
+        let bx0 = tx0.div_euclid(BLOCK);
+        let by0 = ty0 / BLOCK;
+
+        // Best squared distance in scaled degrees, the point, and the edge.
+        let mut best: Option<(f64, [f64; 2], u32)> = None;
+
+        for r in 0..=BNY {
+            if r > 1 {
+                if let Some((d2, _, _)) = best {
+                    let lb = (r - 1) as f64 * BLOCK as f64 * TILE * c;
+                    if lb * lb > d2 {
+                        break;
+                    }
+                }
+            }
+
+            for (bx, by) in ring_blocks(bx0, by0, r) {
+                if !self.blocks[(by * BNX + bx.rem_euclid(BNX)) as usize] {
+                    continue;
+                }
+
+                for ty in (by * BLOCK)..((by + 1) * BLOCK) {
+                    for tx in (bx * BLOCK)..((bx + 1) * BLOCK) {
+                        for (e, a, b) in self.tile_edges(tx, ty) {
+                            if self.is_seam(e) {
+                                continue;
+                            }
+
+                            let (d2, p) = nearest_on_segment(x, y, c, a, b);
+                            if best.map_or(true, |(bd2, _, _)| d2 < bd2) {
+                                best = Some((d2, p, e));
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        best.map(|(_, p, e)| {
+            let (normal_x, normal_y) = self.normal(e);
+
+            Nearest {
+                x: modulate_longitude(p[0]),
+                y: p[1],
+                distance: haversine(x, y, p[0], p[1]),
+                bearing: bearing(x, y, p[0], p[1]),
+                normal_x,
+                normal_y,
+            }
+        })
+    }
+
+    /// Find where the segment from (x0, y0) to (x1, y1) first meets the coastline.
+    ///
+    /// `x1` may be outside [-180, 180] for segments crossing the antimeridian.
+    ///
+    /// Returns the fraction of the segment before it meets the coastline, or `None` if it does
+    /// not.
+    pub fn first_crossing(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Option<f64> {
+        let mut first: Option<f64> = None;
+
+        segment_tiles([x0, y0], [x1, y1], |tx, ty| {
+            for (e, a, b) in self.tile_edges(tx, ty) {
+                if self.is_seam(e) {
+                    continue;
+                }
+
+                if let Some(t) = segment_intersection([x0, y0], [x1, y1], a, b) {
+                    if first.map_or(true, |f| t < f) {
+                        first = Some(t);
+                    }
+                }
+            }
+        });
+
+        first
+    }
+
+    /// Area of land (square degrees) within the convex polygon `poly`.
+    ///
+    /// The polygon is given counter-clockwise without repeating the first point, and may be
+    /// outside [-180, 180] for polygons crossing the antimeridian. The area is integrated along
+    /// the boundary of the land within the polygon: the coastline inside the polygon, and the
+    /// parts of the edges of the polygon that are on land. `is_land` is used to check which parts
+    /// of the edges of the polygon are on land.
+    pub fn land_area<F>(&self, poly: &[[f64; 2]], is_land: F) -> f64
+    where
+        F: Fn(f64, f64) -> bool,
+    {
+        let n = poly.len();
+        let o = poly[0];
+
+        // Contribution to the area from the directed segment from `p` to `q`.
+        let area = |p: [f64; 2], q: [f64; 2]| {
+            ((p[0] - o[0]) * (q[1] - o[1]) - (q[0] - o[0]) * (p[1] - o[1])) / 2.
+        };
+
+        let poly_area: f64 = (0..n).map(|i| area(poly[i], poly[(i + 1) % n])).sum();
+
+        let edges = self.bbox_edges(poly);
+
+        if edges.is_empty() {
+            let cx = poly.iter().map(|p| p[0]).sum::<f64>() / n as f64;
+            let cy = poly.iter().map(|p| p[1]).sum::<f64>() / n as f64;
+
+            return if is_land(cx, cy) { poly_area } else { 0. };
+        }
+
+        let mut land = 0.;
+
+        // The coastline inside the polygon, with land on the left.
+        for &(e, a, b) in &edges {
+            let (a, b) = if self.land_on_left(e) { (a, b) } else { (b, a) };
+
+            if let Some((p, q)) = clip_segment(poly, a, b) {
+                land += area(p, q);
+            }
+        }
+
+        // The edges of the polygon on land.
+        let mut ts = Vec::new();
+
+        for i in 0..n {
+            let (v, w) = (poly[i], poly[(i + 1) % n]);
+            let d = [w[0] - v[0], w[1] - v[1]];
+            let l = d[0].hypot(d[1]);
+
+            if l == 0. {
+                continue;
+            }
+
+            ts.clear();
+            ts.push(0.);
+            ts.push(1.);
+
+            for &(_, a, b) in &edges {
+                crossings(v, w, a, b, &mut ts);
+            }
+
+            ts.sort_by(|a, b| a.total_cmp(b));
+            ts.dedup();
+
+            // Just inside the polygon.
+            let nudge = [-d[1] / l * 1e-9 * l, d[0] / l * 1e-9 * l];
+
+            for t in ts.windows(2) {
+                let m = (t[0] + t[1]) / 2.;
+                let (mx, my) = (v[0] + m * d[0] + nudge[0], v[1] + m * d[1] + nudge[1]);
+
+                if is_land(mx, my) {
+                    let p = [v[0] + t[0] * d[0], v[1] + t[0] * d[1]];
+                    let q = [v[0] + t[1] * d[0], v[1] + t[1] * d[1]];
+                    land += area(p, q);
+                }
+            }
+        }
+
+        land.clamp(0., poly_area)
+    }
+
+    /// All edges in the tiles overlapping the bounding box of the polygon, each edge only once.
+    fn bbox_edges(&self, poly: &[[f64; 2]]) -> Vec<(u32, [f64; 2], [f64; 2])> {
+        let (mut xmin, mut xmax) = (f64::INFINITY, f64::NEG_INFINITY);
+        let (mut ymin, mut ymax) = (f64::INFINITY, f64::NEG_INFINITY);
+
+        for p in poly {
+            xmin = xmin.min(p[0]);
+            xmax = xmax.max(p[0]);
+            ymin = ymin.min(p[1]);
+            ymax = ymax.max(p[1]);
+        }
+
+        let tx0 = ((xmin + 180.) / TILE).floor() as i64;
+        let tx1 = ((xmax + 180.) / TILE).floor() as i64;
+
+        let mut edges = Vec::new();
+
+        for ty in tile_y(ymin)..=tile_y(ymax) {
+            for tx in tx0..=tx1 {
+                edges.extend(self.tile_edges(tx, ty));
+            }
+        }
+
+        edges.sort_by(|a, b| (a.0, a.1[0]).partial_cmp(&(b.0, b.1[0])).unwrap());
+        edges.dedup_by(|a, b| a.0 == b.0 && a.1[0] == b.1[0]);
+
+        edges
+    }
+
+    /// Whether land is on the left of edge `e`.
+    fn land_on_left(&self, e: u32) -> bool {
+        self.rings[self.rings.partition_point(|r| r.start <= e) - 1].land_on_left
+    }
+
+    /// Unit normal of edge `e` pointing out of land, in a local east, north frame.
+    fn normal(&self, e: u32) -> (f64, f64) {
+        let a = self.points[e as usize];
+        let b = self.points[e as usize + 1];
+
+        let c = ((a[1] + b[1]) / 2.).to_radians().cos();
+        let (vx, vy) = ((b[0] - a[0]) * c, b[1] - a[1]);
+        let l = vx.hypot(vy);
+        if l == 0. {
+            return (0., 0.);
+        }
+
+        if self.land_on_left(e) {
+            (vy / l, -vx / l)
+        } else {
+            (-vy / l, vx / l)
+        }
+    }
+
+    /// Edges along the antimeridian or along the poles are where the polygons have been cut, and
+    /// are not part of the real coastline.
+    fn is_seam(&self, e: u32) -> bool {
+        is_seam(self.points[e as usize], self.points[e as usize + 1])
+    }
+
+    /// Iterate over the edges in tile (tx, ty), where `tx` is not wrapped. The edges are shifted
+    /// to the same side of the antimeridian as the tile.
+    fn tile_edges(&self, tx: i64, ty: i64) -> impl Iterator<Item = (u32, [f64; 2], [f64; 2])> + '_ {
+        let w = tx.rem_euclid(TNX);
+        let shift = (tx - w) / TNX;
+
+        self.tiles
+            .get(&(ty * TNX + w))
+            .map(|es| es.as_slice())
+            .unwrap_or(&[])
+            .iter()
+            .map(move |e| {
+                let dx = (shift + e.wrap as i64) as f64 * 360.;
+                let a = self.points[e.edge as usize];
+                let b = self.points[e.edge as usize + 1];
+                (e.edge, [a[0] + dx, a[1]], [b[0] + dx, b[1]])
+            })
+    }
+}
+
+/// Great-circle distance (m) between two points.
+pub fn haversine(x0: f64, y0: f64, x1: f64, y1: f64) -> f64 {
+    let (p0, p1) = (y0.to_radians(), y1.to_radians());
+    let dp = p1 - p0;
+    let dl = (x1 - x0).to_radians();
+
+    let a = (dp / 2.).sin().powi(2) + p0.cos() * p1.cos() * (dl / 2.).sin().powi(2);
+    2. * EARTH_RADIUS * a.sqrt().min(1.).asin()
+}
+
+/// Initial bearing (degrees clockwise from north) on the great-circle from the first to the
+/// second point.
+pub fn bearing(x0: f64, y0: f64, x1: f64, y1: f64) -> f64 {
+    let (p0, p1) = (y0.to_radians(), y1.to_radians());
+    let dl = (x1 - x0).to_radians();
+
+    let b = (dl.sin() * p1.cos()).atan2(p0.cos() * p1.sin() - p0.sin() * p1.cos() * dl.cos());
+    b.to_degrees().rem_euclid(360.)
+}
+
+/// Fraction along the segment from `p0` to `p1` where it first meets the segment from `q0` to
+/// `q1`, if they meet.
+fn segment_intersection(p0: [f64; 2], p1: [f64; 2], q0: [f64; 2], q1: [f64; 2]) -> Option<f64> {
+    let cross = |a: [f64; 2], b: [f64; 2]| a[0] * b[1] - a[1] * b[0];
+    let dot = |a: [f64; 2], b: [f64; 2]| a[0] * b[0] + a[1] * b[1];
+
+    let r = [p1[0] - p0[0], p1[1] - p0[1]];
+    let s = [q1[0] - q0[0], q1[1] - q0[1]];
+    let qp = [q0[0] - p0[0], q0[1] - p0[1]];
+
+    let d = cross(r, s);
+
+    if d == 0. {
+        // Parallel, only meet if they are on the same line.
+        let rr = dot(r, r);
+        if cross(qp, r) != 0. || rr == 0. {
+            return None;
+        }
+
+        let t0 = dot(qp, r) / rr;
+        let t1 = t0 + dot(s, r) / rr;
+        let (lo, hi) = (t0.min(t1), t0.max(t1));
+
+        if hi < 0. || lo > 1. {
+            None
+        } else {
+            Some(lo.max(0.))
+        }
+    } else {
+        let t = cross(qp, s) / d;
+        let u = cross(qp, r) / d;
+
+        if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u) {
+            Some(t)
+        } else {
+            None
+        }
+    }
+}
+
+/// Add the fractions along the segment from `p0` to `p1` where it meets the segment from `q0` to
+/// `q1` to `ts`. Where the segments overlap both ends of the overlap are added.
+fn crossings(p0: [f64; 2], p1: [f64; 2], q0: [f64; 2], q1: [f64; 2], ts: &mut Vec<f64>) {
+    let cross = |a: [f64; 2], b: [f64; 2]| a[0] * b[1] - a[1] * b[0];
+    let dot = |a: [f64; 2], b: [f64; 2]| a[0] * b[0] + a[1] * b[1];
+
+    let r = [p1[0] - p0[0], p1[1] - p0[1]];
+    let s = [q1[0] - q0[0], q1[1] - q0[1]];
+    let qp = [q0[0] - p0[0], q0[1] - p0[1]];
+
+    let d = cross(r, s);
+
+    if d == 0. {
+        let rr = dot(r, r);
+        if cross(qp, r) != 0. || rr == 0. {
+            return;
+        }
+
+        let t0 = dot(qp, r) / rr;
+        let t1 = t0 + dot(s, r) / rr;
+        let (lo, hi) = (t0.min(t1), t0.max(t1));
+
+        if hi >= 0. && lo <= 1. {
+            ts.push(lo.max(0.));
+            ts.push(hi.min(1.));
+        }
+    } else {
+        let t = cross(qp, s) / d;
+        let u = cross(qp, r) / d;
+
+        if (0. ..=1.).contains(&t) && (0. ..=1.).contains(&u) {
+            ts.push(t);
+        }
+    }
+}
+
+/// Clip the segment from `a` to `b` to the convex counter-clockwise polygon `poly`. Parts of the
+/// segment along the edges of the polygon are left out.
+fn clip_segment(poly: &[[f64; 2]], a: [f64; 2], b: [f64; 2]) -> Option<([f64; 2], [f64; 2])> {
+    let cross = |a: [f64; 2], b: [f64; 2]| a[0] * b[1] - a[1] * b[0];
+
+    let n = poly.len();
+    let d = [b[0] - a[0], b[1] - a[1]];
+    let (mut t0, mut t1) = (0f64, 1f64);
+
+    for i in 0..n {
+        let (v, w) = (poly[i], poly[(i + 1) % n]);
+        let e = [w[0] - v[0], w[1] - v[1]];
+
+        // Inside is to the left of the edge: num + t * den >= 0.
+        let num = cross(e, [a[0] - v[0], a[1] - v[1]]);
+        let den = cross(e, d);
+
+        if den == 0. {
+            if num < 0. {
+                return None;
+            }
+        } else if den > 0. {
+            t0 = t0.max(-num / den);
+        } else {
+            t1 = t1.min(-num / den);
+        }
+    }
+
+    if t0 >= t1 {
+        return None;
+    }
+
+    let p = [a[0] + t0 * d[0], a[1] + t0 * d[1]];
+    let q = [a[0] + t1 * d[0], a[1] + t1 * d[1]];
+
+    // Along an edge of the polygon, this is accounted for by the edges of the polygon.
+    for i in 0..n {
+        let (v, w) = (poly[i], poly[(i + 1) % n]);
+        let e = [w[0] - v[0], w[1] - v[1]];
+        let tol = 1e-9 * (e[0] * e[0] + e[1] * e[1]);
+
+        if cross(e, [p[0] - v[0], p[1] - v[1]]).abs() <= tol
+            && cross(e, [q[0] - v[0], q[1] - v[1]]).abs() <= tol
+        {
+            return None;
+        }
+    }
+
+    Some((p, q))
+}
+
+/// The reference point of tile (tx, ty), placed off the centre of the tile so that it is unlikely
+/// to line up with the points of the shapes.
+fn reference_point(tx: i64, ty: i64) -> [f64; 2] {
+    [
+        (tx as f64 + 0.5123456789) * TILE - 180.,
+        (ty as f64 + 0.4876543211) * TILE - 90.,
+    ]
+}
+
+/// Check if the segment from `p` to `q` crosses the edge from `a` to `b`, the ends of the edge on
+/// the line through `p` and `q` are taken to be on its left side. Returns `None` if `q` is on the
+/// edge.
+fn crosses(p: [f64; 2], q: [f64; 2], a: [f64; 2], b: [f64; 2]) -> Option<bool> {
+    let orient = |u: [f64; 2], v: [f64; 2], w: [f64; 2]| {
+        (v[0] - u[0]) * (w[1] - u[1]) - (v[1] - u[1]) * (w[0] - u[0])
+    };
+
+    let oq = orient(a, b, q);
+    if oq == 0.
+        && q[0] >= a[0].min(b[0])
+        && q[0] <= a[0].max(b[0])
+        && q[1] >= a[1].min(b[1])
+        && q[1] <= a[1].max(b[1])
+    {
+        return None;
+    }
+
+    let op = orient(a, b, p);
+    let left_a = orient(p, q, a) >= 0.;
+    let left_b = orient(p, q, b) >= 0.;
+
+    Some(left_a != left_b && op * oq < 0.)
+}
+
+/// Edges along the antimeridian or along the poles, see `Coastline::is_seam`.
+fn is_seam(a: [f64; 2], b: [f64; 2]) -> bool {
+    (a[0] == b[0] && a[0].abs() == 180.) || (a[1] == b[1] && a[1].abs() == 90.)
+}
+
+/// Twice the signed area of the ring, positive if the ring is counter-clockwise.
+pub(crate) fn signed_area(ring: &[[f64; 2]]) -> f64 {
+    ring.windows(2)
+        .map(|e| e[0][0] * e[1][1] - e[1][0] * e[0][1])
+        .sum()
+}
+
+/// Check if point `p` is inside the closed ring, by counting the crossings of a ray to the east.
+pub(crate) fn ring_contains(ring: &[[f64; 2]], p: [f64; 2]) -> bool {
+    ring.windows(2)
+        .filter(|e| {
+            let (a, b) = (e[0], e[1]);
+            (a[1] > p[1]) != (b[1] > p[1])
+                && p[0] < a[0] + (b[0] - a[0]) * (p[1] - a[1]) / (b[1] - a[1])
+        })
+        .count()
+        % 2
+        == 1
+}
+
+/// Closest point to (x, y) on the segment from `a` to `b`, using longitudes scaled by `c`.
+/// Returns the squared distance in scaled degrees and the point.
+fn nearest_on_segment(x: f64, y: f64, c: f64, a: [f64; 2], b: [f64; 2]) -> (f64, [f64; 2]) {
+    let (ax, ay) = ((a[0] - x) * c, a[1] - y);
+    let (vx, vy) = ((b[0] - a[0]) * c, b[1] - a[1]);
+
+    let l2 = vx * vx + vy * vy;
+    let t = if l2 > 0. {
+        (-(ax * vx + ay * vy) / l2).clamp(0., 1.)
+    } else {
+        0.
+    };
+
+    let (qx, qy) = (ax + t * vx, ay + t * vy);
+
+    (
+        qx * qx + qy * qy,
+        [a[0] + t * (b[0] - a[0]), a[1] + t * (b[1] - a[1])],
+    )
+}
+
+fn tile_y(y: f64) -> i64 {
+    (((y + 90.) / TILE).floor() as i64).clamp(0, TNY - 1)
+}
+
+/// Call `f` with every tile the segment from `a` to `b` passes through. The x-index of the tile
+/// is not wrapped around the antimeridian.
+fn segment_tiles<F: FnMut(i64, i64)>(a: [f64; 2], b: [f64; 2], mut f: F) {
+    let (ylo, yhi) = (a[1].min(b[1]), a[1].max(b[1]));
+
+    for ty in tile_y(ylo)..=tile_y(yhi) {
+        // The part of the segment within this row of tiles.
+        let (x0, x1) = if a[1] == b[1] {
+            (a[0], b[0])
+        } else {
+            let row0 = (ty as f64 * TILE - 90.).max(ylo);
+            let row1 = ((ty + 1) as f64 * TILE - 90.).min(yhi);
+
+            let at = |y: f64| a[0] + (b[0] - a[0]) * (y - a[1]) / (b[1] - a[1]);
+            (at(row0), at(row1))
+        };
+
+        let tx0 = ((x0.min(x1) + 180.) / TILE).floor() as i64;
+        let tx1 = ((x0.max(x1) + 180.) / TILE).floor() as i64;
+
+        for tx in tx0..=tx1 {
+            f(tx, ty);
+        }
+    }
+}
+
+/// The blocks at Chebyshev distance `r` from block (bx, by). The x-index is not wrapped.
+fn ring_blocks(bx: i64, by: i64, r: i64) -> Vec<(i64, i64)> {
+    let mut blocks = Vec::new();
+
+    for dy in -r..=r {
+        if !(0..BNY).contains(&(by + dy)) {
+            continue;
+        }
+
+        if dy.abs() == r {
+            blocks.extend((-r..=r).map(|dx| (bx + dx, by + dy)));
+        } else {
+            blocks.push((bx - r, by + dy));
+            blocks.push((bx + r, by + dy));
+        }
+    }
+
+    blocks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x0: f64, y0: f64, x1: f64, y1: f64) -> (Vec<[f64; 2]>, bool) {
+        (vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1], [x0, y0]], true)
+    }
+
+    #[test]
+    fn test_nearest_square() {
+        let c = Coastline::from_rings(vec![square(10., 60., 11., 61.)]);
+
+        let n = c.nearest(9., 60.5).unwrap();
+        assert_eq!((n.x, n.y), (10., 60.5));
+        assert!((n.distance - haversine(9., 60.5, 10., 60.5)).abs() < 1e-6);
+
+        let n = c.nearest(10.5, 65.).unwrap();
+        assert_eq!((n.x, n.y), (10.5, 61.));
+
+        // Far away, closest across the antimeridian
+        let n = c.nearest(-170., -60.).unwrap();
+        assert_eq!((n.x, n.y), (11., 60.));
+    }
+
+    #[test]
+    fn test_nearest_normal() {
+        let c = Coastline::from_rings(vec![square(10., 60., 11., 61.)]);
+
+        let n = c.nearest(9., 60.5).unwrap();
+        assert!((n.bearing - 90.).abs() < 1.);
+        assert!((n.normal_x + 1.).abs() < 1e-9 && n.normal_y.abs() < 1e-9);
+
+        let n = c.nearest(10.5, 62.).unwrap();
+        assert!((n.bearing - 180.).abs() < 1e-9);
+        assert!(n.normal_x.abs() < 1e-9 && (n.normal_y - 1.).abs() < 1e-9);
+
+        // Clockwise ring, and a hole (lake) in the square.
+        let mut cw = square(10., 60., 11., 61.);
+        cw.0.reverse();
+        let mut lake = square(10.25, 60.25, 10.75, 60.75);
+        lake.1 = false;
+
+        let c = Coastline::from_rings(vec![cw, lake]);
+        let n = c.nearest(9., 60.5).unwrap();
+        assert!((n.normal_x + 1.).abs() < 1e-9 && n.normal_y.abs() < 1e-9);
+
+        let n = c.nearest(10.5, 60.3).unwrap();
+        assert_eq!(n.y, 60.25);
+        assert!(n.normal_x.abs() < 1e-9 && (n.normal_y - 1.).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_nearest_across_antimeridian() {
+        let c = Coastline::from_rings(vec![square(-179.5, 0., -179., 1.)]);
+
+        let n = c.nearest(179.8, 0.5).unwrap();
+        assert_eq!((n.x, n.y), (-179.5, 0.5));
+        assert!((n.distance - haversine(179.8, 0.5, 180.5, 0.5)).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_first_crossing() {
+        let c = Coastline::from_rings(vec![square(10., 60., 11., 61.)]);
+
+        assert_eq!(c.first_crossing(9., 60.5, 10.5, 60.5), Some(2. / 3.));
+        assert_eq!(c.first_crossing(12., 60.5, 9., 60.5), Some(1. / 3.));
+        assert_eq!(c.first_crossing(9., 60.5, 9.5, 60.5), None);
+
+        // Along the edge
+        assert_eq!(c.first_crossing(9., 60., 10.5, 60.), Some(2. / 3.));
+
+        // Across the antimeridian
+        let c = Coastline::from_rings(vec![square(-179.5, 0., -179., 1.)]);
+        assert_eq!(c.first_crossing(179.5, 0.5, 180.75, 0.5), Some(0.8));
+    }
+
+    #[test]
+    fn test_land_area() {
+        let c = Coastline::from_rings(vec![square(10., 60., 11., 61.)]);
+        let is_land = |x: f64, y: f64| x > 10. && x < 11. && y > 60. && y < 61.;
+
+        let rect = |x0: f64, y0: f64, x1: f64, y1: f64| [[x0, y0], [x1, y0], [x1, y1], [x0, y1]];
+
+        let a = c.land_area(&rect(10.5, 60.5, 11.5, 61.5), is_land);
+        assert!((a - 0.25).abs() < 1e-9);
+
+        // Inside, outside and covering.
+        assert!((c.land_area(&rect(10.2, 60.2, 10.4, 60.4), is_land) - 0.04).abs() < 1e-9);
+        assert_eq!(c.land_area(&rect(12., 60.2, 12.5, 60.4), is_land), 0.);
+        assert!((c.land_area(&rect(9., 59., 12., 62.), is_land) - 1.).abs() < 1e-9);
+
+        // Sharing edges with the land.
+        assert!((c.land_area(&rect(10., 60., 10.5, 61.), is_land) - 0.5).abs() < 1e-9);
+        assert!((c.land_area(&rect(11., 60., 11.5, 61.), is_land)).abs() < 1e-9);
+
+        // A triangle, and a hole.
+        let tri = [[10.5, 59.5], [11.5, 60.5], [10.5, 60.5]];
+        assert!((c.land_area(&tri, is_land) - 0.25).abs() < 1e-9);
+
+        let mut lake = square(10.25, 60.25, 10.75, 60.75);
+        lake.1 = false;
+        let c = Coastline::from_rings(vec![square(10., 60., 11., 61.), lake]);
+        let is_land = |x: f64, y: f64| {
+            x > 10.
+                && x < 11.
+                && y > 60.
+                && y < 61.
+                && !(x > 10.25 && x < 10.75 && y > 60.25 && y < 60.75)
+        };
+        assert!((c.land_area(&rect(10., 60., 10.5, 60.5), is_land) - (0.25 - 0.0625)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_land_area_across_antimeridian() {
+        // Land cut along the antimeridian, like the GSHHG polygons.
+        let c = Coastline::from_rings(vec![
+            square(179., 0., 180., 1.),
+            square(-180., 0., -179., 1.),
+        ]);
+        let is_land = |x: f64, y: f64| {
+            let x = modulate_longitude(x);
+            x.abs() > 179. && y > 0. && y < 1.
+        };
+
+        let a = c.land_area(
+            &[[179.5, 0.], [180.5, 0.], [180.5, 1.], [179.5, 1.]],
+            is_land,
+        );
+        assert!((a - 1.).abs() < 1e-9);
+
+        let a = c.land_area(&[[179.5, 0.], [180., 0.], [180., 1.], [179.5, 1.]], is_land);
+        assert!((a - 0.5).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_seams_are_not_coast() {
+        let c = Coastline::from_rings(vec![square(170., -90., 180., -80.)]);
+
+        let n = c.nearest(179.9, -80.5).unwrap();
+        assert!((n.x - 179.9).abs() < 1e-9);
+        assert_eq!(n.y, -80.);
+    }
+
+    #[test]
+    fn test_edges() {
+        let coast = Coastline::from_rings(vec![square(0., 0., 1., 1.), square(170., 0., 180., 1.)]);
+
+        assert_eq!(coast.rings()[1], square(170., 0., 180., 1.));
+
+        let edges = coast.edges().collect::<Vec<_>>();
+        assert_eq!(edges.len(), 8);
+        assert_eq!(edges.iter().filter(|e| e.2).count(), 1);
+        assert_eq!(edges[4].0, [170., 0.]);
+    }
+
+    #[test]
+    fn test_scan() {
+        let coast = Coastline::from_rings(vec![
+            square(0., 0., 2., 2.),
+            (
+                vec![[0.5, 0.5], [0.5, 1.5], [1.5, 1.5], [1.5, 0.5], [0.5, 0.5]],
+                false,
+            ),
+            square(170., 1., 180., 3.),
+        ]);
+
+        let mut rows = Vec::new();
+        coast.scan(vec![-1., 0., 1., 2.5, 3.], |lat, xs| {
+            rows.push((lat, xs.to_vec()))
+        });
+
+        assert_eq!(
+            rows,
+            vec![
+                (-1., vec![]),
+                (0., vec![0., 2.]),
+                (1., vec![0., 0.5, 1.5, 2., 170., 180.]),
+                (2.5, vec![170., 180.]),
+                (3., vec![]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_contains() {
+        let lake = (
+            vec![
+                [10.2, 60.2],
+                [10.4, 60.2],
+                [10.4, 60.4],
+                [10.2, 60.4],
+                [10.2, 60.2],
+            ],
+            false,
+        );
+        let c = Coastline::from_rings(vec![
+            square(10., 60., 11., 61.),
+            lake,
+            square(179., 0., 180., 1.),
+            square(-180., 0., -179., 1.),
+        ]);
+
+        assert!(c.contains(10.5, 60.5));
+        assert!(c.contains(10.1, 60.3));
+        assert!(!c.contains(10.3, 60.3));
+        assert!(!c.contains(11.5, 60.5));
+        assert!(!c.contains(9.99, 60.5));
+
+        // On the coastline, and on a vertex.
+        assert!(!c.contains(11., 60.5));
+        assert!(!c.contains(11., 61.));
+
+        // Along the edge of the tiles.
+        assert!(c.contains(10.125, 60.5));
+        assert!(c.contains(10.5, 60.125));
+        assert!(!c.contains(12., 60.125));
+
+        assert!(c.contains(180., 0.5));
+        assert!(c.contains(-180., 0.5));
+        assert!(c.contains(179.99, 0.5));
+        assert!(!c.contains(179.99, 1.5));
+        assert!(!c.contains(-180., 1.5));
+    }
+
+    #[test]
+    fn test_contains_matches_crossings() {
+        // A jagged island with a lake, with points on the grid of the tiles.
+        let mut ring = Vec::new();
+        for i in 0..64 {
+            let a = i as f64 / 64. * std::f64::consts::TAU;
+            let r = if i % 2 == 0 { 1. } else { 0.625 };
+            ring.push([5. + r * a.cos(), 5. + r * a.sin()]);
+        }
+        ring.push(ring[0]);
+        let lake = vec![
+            [4.75, 4.75],
+            [4.75, 5.25],
+            [5.25, 5.25],
+            [5.25, 4.75],
+            [4.75, 4.75],
+        ];
+
+        let c = Coastline::from_rings(vec![(ring.clone(), true), (lake.clone(), false)]);
+
+        let inside = |ring: &[[f64; 2]], x: f64, y: f64| ring_contains(ring, [x, y]);
+
+        for i in 0..200 {
+            for j in 0..200 {
+                let (x, y) = (3.8 + i as f64 * 0.0123, 3.8 + j as f64 * 0.0123);
+                assert_eq!(
+                    c.contains(x, y),
+                    inside(&ring, x, y) && !inside(&lake, x, y),
+                    "{} {}",
+                    x,
+                    y
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_ring_blocks_cover() {
+        assert_eq!(ring_blocks(10, 10, 0).len(), 1);
+        assert_eq!(ring_blocks(10, 10, 1).len(), 8);
+        assert_eq!(ring_blocks(10, 10, 2).len(), 16);
+        assert_eq!(ring_blocks(10, 0, 1).len(), 5);
+    }
+}
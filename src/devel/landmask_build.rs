@@ -0,0 +1,292 @@
+//! Build a landmask dataset from land polygons.
+//!
+//! Reads the land polygons from WKB (optionally xz-compressed), GeoJSON, a shapefile or a GSHHG
+//! binary file, and writes the pair of files that `RoaringMask::from_compressed` and
+//! `Shapes::from_compressed` load: `<name>_mask.tbmap.xz` and `<name>.wkb.xz`. The mask file
+//! records its grid and where the data comes from.
+#[cfg(feature = "geos")]
+use geos::Geom;
+use roaring_landmask::{
+    geojson, gshhg, shapefile, wkb, Grid, LandmaskError, MaskMetadata, RasterRule, RoaringMask,
+};
+#[cfg(feature = "geos")]
+use roaring_landmask::{shapes, Shapes};
+use sha2::{Digest, Sha256};
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::{self, prelude::*};
+use std::path::{Path, PathBuf};
+use std::process;
+
+const USAGE: &str = "\
+Usage: landmask-build [OPTIONS] <INPUT>
+
+Build <name>_mask.tbmap.xz and <name>.wkb.xz from the land polygons in INPUT, which is
+WKB (.wkb, .wkb.xz), GeoJSON (.geojson, .json), a shapefile (.shp) or a GSHHG binary file
+(.b, e.g. gshhs_f.b).
+
+Options:
+  -n, --name <NAME>         Name of the dataset [default: the name of INPUT]
+  -o, --out-dir <DIR>       Directory to write the files to [default: .]
+  -r, --resolution <DEG>    Size of the cells of the mask in degrees, e.g. 1/240 [default: 1/240]
+  -s, --simplify <DEG>      Simplify the polygons with this tolerance, preserving topology
+                            (needs the geos feature)
+  -b, --bbox <X0,Y0,X1,Y1>  Only keep the polygons within the bounding box, and make the mask
+                            on a regional grid covering it (needs the geos feature)
+  -l, --lakes-as-water      Keep the lakes of a GSHHG file as water, instead of land
+  -p, --provider <NAME>     Provider of the data, stored in the mask [default: NAME]
+  -v, --data-version <VER>  Version of the data, stored in the mask
+  -L, --licence <LICENCE>   Licence of the data, stored in the mask
+  -w, --with-wkb            Also store the WKB of the shapes in the mask file
+  -c, --checksum            Write the SHA-256 checksums of the files to <name>.sha256
+  -h, --help                Print this help
+";
+
+struct Args {
+    input: PathBuf,
+    name: String,
+    out_dir: PathBuf,
+    resolution: f64,
+    // Only used with GEOS, see `parse_args`.
+    #[cfg_attr(not(feature = "geos"), allow(dead_code))]
+    simplify: Option<f64>,
+    bbox: Option<[f64; 4]>,
+    lakes_as_water: bool,
+    metadata: MaskMetadata,
+    with_wkb: bool,
+    checksum: bool,
+}
+
+fn main() {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n\n{}", e, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(e) = build(&args) {
+        eprintln!("error: {}", e);
+        process::exit(1);
+    }
+}
+
+fn parse_args<I: Iterator<Item = String>>(mut argv: I) -> Result<Args, String> {
+    let mut input = None;
+    let mut name = None;
+    let mut out_dir = PathBuf::from(".");
+    let mut resolution = 1. / 240.;
+    let mut simplify = None;
+    let mut bbox = None;
+    let mut lakes_as_water = false;
+    let mut provider = None;
+    let mut data_version = String::new();
+    let mut licence = String::new();
+    let mut with_wkb = false;
+    let mut checksum = false;
+
+    while let Some(arg) = argv.next() {
+        let mut value = || argv.next().ok_or_else(|| format!("{} needs a value", arg));
+
+        match arg.as_str() {
+            "-n" | "--name" => name = Some(value()?),
+            "-o" | "--out-dir" => out_dir = PathBuf::from(value()?),
+            "-r" | "--resolution" => resolution = parse_degrees(&value()?)?,
+            "-s" | "--simplify" => simplify = Some(parse_degrees(&value()?)?),
+            "-b" | "--bbox" => {
+                let v = value()?;
+                let b = v
+                    .split(',')
+                    .map(parse_degrees)
+                    .collect::<Result<Vec<_>, _>>()?;
+                bbox = Some(
+                    b.try_into()
+                        .map_err(|_| format!("invalid bounding box: {}", v))?,
+                );
+            }
+            "-l" | "--lakes-as-water" => lakes_as_water = true,
+            "-p" | "--provider" => provider = Some(value()?),
+            "-v" | "--data-version" => data_version = value()?,
+            "-L" | "--licence" => licence = value()?,
+            "-w" | "--with-wkb" => with_wkb = true,
+            "-c" | "--checksum" => checksum = true,
+            "-h" | "--help" => {
+                print!("{}", USAGE);
+                process::exit(0);
+            }
+            _ if arg.starts_with('-') => return Err(format!("unknown option: {}", arg)),
+            _ if input.is_none() => input = Some(PathBuf::from(arg)),
+            _ => return Err(format!("unexpected argument: {}", arg)),
+        }
+    }
+
+    let input = input.ok_or("missing INPUT")?;
+
+    // Simplifying and clipping the polygons is done with GEOS.
+    if cfg!(not(feature = "geos")) && (simplify.is_some() || bbox.is_some()) {
+        return Err("--simplify and --bbox need landmask-build built with the geos feature".into());
+    }
+    let name = match name {
+        Some(name) => name,
+        None => dataset_name(&input).ok_or("cannot name the dataset, use --name")?,
+    };
+
+    let metadata = MaskMetadata {
+        provider: provider.unwrap_or_else(|| name.clone()),
+        data_version,
+        licence,
+    };
+
+    Ok(Args {
+        input,
+        name,
+        out_dir,
+        resolution,
+        simplify,
+        bbox,
+        lakes_as_water,
+        metadata,
+        with_wkb,
+        checksum,
+    })
+}
+
+/// Parse degrees, either as a number or as a fraction like `1/240`.
+fn parse_degrees(s: &str) -> Result<f64, String> {
+    let v = match s.split_once('/') {
+        Some((a, b)) => a
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .zip(b.trim().parse::<f64>().ok())
+            .map(|(a, b)| a / b),
+        None => s.trim().parse().ok(),
+    };
+
+    v.filter(|v| v.is_finite())
+        .ok_or_else(|| format!("invalid number of degrees: {}", s))
+}
+
+/// The name of the input file without its extensions, e.g. `gshhg` for `gshhg.wkb.xz`.
+fn dataset_name(input: &Path) -> Option<String> {
+    let name = input.file_name()?.to_str()?;
+    let name = name.split('.').next()?;
+
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn build(args: &Args) -> Result<(), LandmaskError> {
+    println!("reading {:?}..", args.input);
+    let rings = read_rings(&args.input, args.lakes_as_water)?;
+    println!("read {} rings", rings.len());
+
+    #[cfg(feature = "geos")]
+    let rings = if args.simplify.is_some() || args.bbox.is_some() {
+        let mut geom = shapes::geometry_from_rings(&rings)?;
+
+        if let Some([x0, y0, x1, y1]) = args.bbox {
+            println!("clipping to bounding box..");
+            let bbox = geos::Geometry::new_from_wkt(&format!(
+                "POLYGON(({x0} {y0}, {x1} {y0}, {x1} {y1}, {x0} {y1}, {x0} {y0}))"
+            ))?;
+            geom = geom.intersection(&bbox)?;
+        }
+
+        if let Some(tolerance) = args.simplify {
+            println!("simplifying with tolerance {}..", tolerance);
+            geom = geom.topology_preserve_simplify(tolerance)?;
+        }
+
+        let rings = Shapes::from_geom(geom)?.rings()?;
+        println!("kept {} rings", rings.len());
+        rings
+    } else {
+        rings
+    };
+
+    fs::create_dir_all(&args.out_dir)?;
+
+    let grid = match args.bbox {
+        Some([x0, y0, x1, y1]) => Grid::regional(x0, y0, x1, y1, args.resolution)?,
+        None => Grid::global(args.resolution)?,
+    };
+
+    println!("rasterising the mask..");
+    let mask = RoaringMask::from_rings_with_grid(rings.clone(), grid, RasterRule::AnyLand)?
+        .with_metadata(args.metadata.clone());
+    println!("land cells: {}", mask.len());
+
+    let wkb = wkb::write_rings(&rings);
+
+    let mask_path = args.out_dir.join(format!("{}_mask.tbmap.xz", args.name));
+    println!("writing {:?}..", mask_path);
+    if args.with_wkb {
+        mask.to_compressed_with_wkb(&mask_path, &wkb)?;
+    } else {
+        mask.to_compressed(&mask_path)?;
+    }
+
+    let wkb_path = args.out_dir.join(format!("{}.wkb.xz", args.name));
+    println!("writing {:?}..", wkb_path);
+    {
+        let fd = File::create(&wkb_path)?;
+        let mut fd = xz2::write::XzEncoder::new(io::BufWriter::new(fd), 9);
+        fd.write_all(&wkb)?;
+        fd.finish()?.flush()?;
+    }
+
+    if args.checksum {
+        let mut sums = String::new();
+        for path in [&mask_path, &wkb_path] {
+            let digest = Sha256::digest(fs::read(path)?);
+            let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+            let line = format!("{}  {}\n", hex, path.file_name().unwrap().to_string_lossy());
+
+            print!("{}", line);
+            sums.push_str(&line);
+        }
+
+        let sums_path = args.out_dir.join(format!("{}.sha256", args.name));
+        println!("writing {:?}..", sums_path);
+        fs::write(sums_path, sums)?;
+    }
+
+    Ok(())
+}
+
+/// Read the rings of the land polygons, by the extension of the file.
+#[allow(clippy::type_complexity)]
+fn read_rings(
+    path: &Path,
+    lakes_as_water: bool,
+) -> Result<Vec<(Vec<[f64; 2]>, bool)>, LandmaskError> {
+    let name = path.to_string_lossy().to_lowercase();
+
+    let mut buf = Vec::new();
+    if name.ends_with(".xz") {
+        xz2::read::XzDecoder::new(File::open(path)?).read_to_end(&mut buf)?;
+    } else {
+        File::open(path)?.read_to_end(&mut buf)?;
+    }
+    let name = name.trim_end_matches(".xz");
+
+    let rings = if name.ends_with(".wkb") {
+        wkb::read_rings(&buf)?
+    } else if name.ends_with(".geojson") || name.ends_with(".json") {
+        geojson::read_rings(&buf)?
+    } else if name.ends_with(".shp") {
+        shapefile::read_rings(&buf)?
+    } else if name.ends_with(".b") {
+        let polygons = gshhg::read_polygons(&buf)?;
+        println!("read {} GSHHG polygons", polygons.len());
+        gshhg::rings(&polygons, lakes_as_water)
+    } else {
+        return Err(LandmaskError::InvalidInput(format!(
+            "unknown input format: {:?}",
+            path
+        )));
+    };
+
+    Ok(rings)
+}
@@ -4,16 +4,17 @@ import numpy as np
 import cartopy.crs as ccrs
 from roaring_landmask import RoaringLandmask
 from roaring_landmask import LandmaskProvider
+from roaring_landmask import RasterRule
 
 
-x = np.arange(-180, 180, .1)
-y = np.arange(-90, 90, .1)
+# Cell edges
+x = np.linspace(-180, 180, 3601)
+y = np.linspace(-90, 90, 1801)
 
 xx, yy = np.meshgrid(x,y)
 
 gshhg_landmask = RoaringLandmask.new_with_provider(LandmaskProvider.Gshhg)
-land = gshhg_landmask.contains_many(xx.ravel(), yy.ravel())
-land = land.reshape(xx.shape)
+land = gshhg_landmask.rasterize(-180, 180, len(x) - 1, -90, 90, len(y) - 1, RasterRule.Centre)
 
 cmap = matplotlib.colors.ListedColormap(['b', 'g'])
 
@@ -27,8 +28,7 @@ ax.set_title('The Earth GSHHG', pad=20)
 fig.savefig('the_earth_gshhg.png')
 
 osm_landmask = RoaringLandmask.new_with_provider(LandmaskProvider.Osm)
-land = osm_landmask.contains_many(xx.ravel(), yy.ravel())
-land = land.reshape(xx.shape)
+land = osm_landmask.rasterize(-180, 180, len(x) - 1, -90, 90, len(y) - 1, RasterRule.Centre)
 
 fig = plt.figure(dpi = 600, figsize = (20, 10))
 ax = fig.add_subplot(1, 1, 1, projection=ccrs.PlateCarree())
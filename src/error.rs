@@ -0,0 +1,223 @@
+//! The errors of the landmask.
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::fmt;
+use std::io;
+
+#[derive(Debug)]
+pub enum LandmaskError {
+    /// The longitude or latitude is NaN or infinite.
+    NonFinite { x: f64, y: f64 },
+    /// The latitude is outside [-90, 90].
+    LatitudeOutOfRange(f64),
+    /// The longitude is outside the range of the `LongitudeConvention`.
+    LongitudeOutOfRange(f64),
+    /// The embedded or given data is missing or corrupt.
+    InvalidData(String),
+    /// The arguments are not valid.
+    InvalidInput(String),
+    /// Reading the data failed.
+    Io(io::Error),
+    /// A GEOS operation failed.
+    #[cfg(feature = "geos")]
+    Geos(geos::Error),
+}
+
+impl fmt::Display for LandmaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use LandmaskError::*;
+
+        match self {
+            NonFinite { x, y } => write!(f, "coordinates are not finite: ({}, {})", x, y),
+            LatitudeOutOfRange(y) => write!(f, "latitude {} is outside [-90, 90]", y),
+            LongitudeOutOfRange(x) => {
+                write!(f, "longitude {} is outside the longitude convention", x)
+            }
+            InvalidData(msg) => write!(f, "invalid data: {}", msg),
+            InvalidInput(msg) => write!(f, "invalid input: {}", msg),
+            Io(e) => e.fmt(f),
+            #[cfg(feature = "geos")]
+            Geos(e) => write!(f, "GEOS failed: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for LandmaskError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            LandmaskError::Io(e) => Some(e),
+            #[cfg(feature = "geos")]
+            LandmaskError::Geos(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// I/O errors from decoding corrupt data become `InvalidData`.
+impl From<io::Error> for LandmaskError {
+    fn from(e: io::Error) -> Self {
+        match e.kind() {
+            io::ErrorKind::InvalidData | io::ErrorKind::UnexpectedEof => {
+                LandmaskError::InvalidData(e.to_string())
+            }
+            _ => LandmaskError::Io(e),
+        }
+    }
+}
+
+#[cfg(feature = "geos")]
+impl From<geos::Error> for LandmaskError {
+    fn from(e: geos::Error) -> Self {
+        LandmaskError::Geos(e)
+    }
+}
+
+impl From<LandmaskError> for io::Error {
+    fn from(e: LandmaskError) -> Self {
+        match e {
+            LandmaskError::Io(e) => e,
+            LandmaskError::InvalidData(_) => io::Error::new(io::ErrorKind::InvalidData, e),
+            e => io::Error::new(io::ErrorKind::InvalidInput, e),
+        }
+    }
+}
+
+/// What to do with points that cannot be looked up, e.g. masked or fill values, when checking
+/// many points at once.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InvalidPolicy {
+    /// Return an error for the whole batch.
+    #[default]
+    Raise,
+    /// Treat invalid points as ocean.
+    Ocean,
+    /// Treat invalid points as land.
+    Land,
+}
+
+impl InvalidPolicy {
+    /// Check the points up front if invalid points should give an error.
+    pub(crate) fn check<I>(self, points: I) -> Result<(), LandmaskError>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        if self == InvalidPolicy::Raise {
+            points
+                .into_iter()
+                .try_for_each(|(x, y)| check_coordinates(x, y))?;
+        }
+
+        Ok(())
+    }
+
+    /// The result for a point, using the policy if the point is invalid.
+    pub(crate) fn resolve(self, contains: Result<bool, LandmaskError>) -> bool {
+        contains.unwrap_or(self == InvalidPolicy::Land)
+    }
+}
+
+/// Check that the point (x, y) can be looked up: both coordinates must be finite and the latitude
+/// within [-90, 90]. Any finite longitude is wrapped onto [-180, 180].
+pub(crate) fn check_coordinates(x: f64, y: f64) -> Result<(), LandmaskError> {
+    if !x.is_finite() || !y.is_finite() {
+        Err(LandmaskError::NonFinite { x, y })
+    } else if !(-90. ..=90.).contains(&y) {
+        Err(LandmaskError::LatitudeOutOfRange(y))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    roaring_landmask,
+    InvalidCoordinateError,
+    pyo3::exceptions::PyValueError,
+    "The longitude or latitude is NaN, infinite or out of range."
+);
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    roaring_landmask,
+    InvalidDataError,
+    pyo3::exceptions::PyValueError,
+    "The landmask data is missing or corrupt."
+);
+
+#[cfg(feature = "python")]
+pyo3::create_exception!(
+    roaring_landmask,
+    GeosError,
+    pyo3::exceptions::PyRuntimeError,
+    "A GEOS operation failed."
+);
+
+#[cfg(feature = "python")]
+impl From<LandmaskError> for PyErr {
+    fn from(e: LandmaskError) -> Self {
+        use pyo3::exceptions::PyValueError;
+
+        match e {
+            LandmaskError::NonFinite { .. }
+            | LandmaskError::LatitudeOutOfRange(_)
+            | LandmaskError::LongitudeOutOfRange(_) => {
+                InvalidCoordinateError::new_err(e.to_string())
+            }
+            LandmaskError::InvalidData(_) => InvalidDataError::new_err(e.to_string()),
+            LandmaskError::InvalidInput(_) => PyValueError::new_err(e.to_string()),
+            LandmaskError::Io(e) => e.into(),
+            #[cfg(feature = "geos")]
+            LandmaskError::Geos(_) => GeosError::new_err(e.to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_coordinates() {
+        assert!(check_coordinates(15., 65.6).is_ok());
+        assert!(check_coordinates(540., -90.).is_ok());
+
+        assert!(matches!(
+            check_coordinates(f64::NAN, 0.),
+            Err(LandmaskError::NonFinite { .. })
+        ));
+        assert!(matches!(
+            check_coordinates(0., f64::INFINITY),
+            Err(LandmaskError::NonFinite { .. })
+        ));
+        assert!(matches!(
+            check_coordinates(0., 95.),
+            Err(LandmaskError::LatitudeOutOfRange(_))
+        ));
+    }
+
+    #[test]
+    fn test_invalid_policy() {
+        let points = [(0., 0.), (f64::NAN, 0.)];
+
+        assert!(InvalidPolicy::Raise.check(points).is_err());
+        assert!(InvalidPolicy::Ocean.check(points).is_ok());
+
+        let invalid = || Err(LandmaskError::NonFinite { x: f64::NAN, y: 0. });
+        assert!(!InvalidPolicy::Ocean.resolve(invalid()));
+        assert!(InvalidPolicy::Land.resolve(invalid()));
+        assert!(!InvalidPolicy::Land.resolve(Ok(false)));
+    }
+
+    #[test]
+    fn test_io_error() {
+        let e: LandmaskError = io::Error::new(io::ErrorKind::InvalidData, "corrupt").into();
+        assert!(matches!(e, LandmaskError::InvalidData(_)));
+
+        let e: LandmaskError = io::Error::new(io::ErrorKind::NotFound, "missing").into();
+        assert!(matches!(e, LandmaskError::Io(_)));
+
+        let e: io::Error = LandmaskError::LatitudeOutOfRange(95.).into();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,147 @@
+//! Reading the rings of the land polygons from GeoJSON.
+//!
+//! Geometries, features and feature collections are supported. Only the polygons are read, other
+//! geometries are skipped like for WKB, and only the horizontal coordinates are kept.
+use serde_json::Value;
+use std::io;
+
+/// Read the rings of all the polygons in the GeoJSON, along with whether the ring is an exterior
+/// ring (`true`) or an interior ring (`false`). The interior rings of a polygon follow its
+/// exterior ring, like for `wkb::read_rings`.
+pub fn read_rings(buf: &[u8]) -> io::Result<Vec<(Vec<[f64; 2]>, bool)>> {
+    let value: Value = serde_json::from_slice(buf)?;
+
+    let mut rings = Vec::new();
+    object(&value, &mut rings)?;
+
+    Ok(rings)
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn array(value: &Value) -> io::Result<&[Value]> {
+    value
+        .as_array()
+        .map(Vec::as_slice)
+        .ok_or_else(|| invalid("expected an array in GeoJSON"))
+}
+
+/// Collect the rings of a GeoJSON object.
+fn object(value: &Value, rings: &mut Vec<(Vec<[f64; 2]>, bool)>) -> io::Result<()> {
+    let ty = value
+        .get("type")
+        .and_then(Value::as_str)
+        .ok_or_else(|| invalid("GeoJSON object without a type"))?;
+    let field = |key: &str| {
+        value
+            .get(key)
+            .ok_or_else(|| invalid(&format!("GeoJSON {} without {}", ty, key)))
+    };
+
+    match ty {
+        "FeatureCollection" => {
+            for feature in array(field("features")?)? {
+                object(feature, rings)?;
+            }
+        }
+        "Feature" => match field("geometry")? {
+            Value::Null => {}
+            geometry => object(geometry, rings)?,
+        },
+        "GeometryCollection" => {
+            for geometry in array(field("geometries")?)? {
+                object(geometry, rings)?;
+            }
+        }
+        "Polygon" => polygon(field("coordinates")?, rings)?,
+        "MultiPolygon" => {
+            for p in array(field("coordinates")?)? {
+                polygon(p, rings)?;
+            }
+        }
+        "Point" | "MultiPoint" | "LineString" | "MultiLineString" => {}
+        _ => return Err(invalid("unsupported GeoJSON type")),
+    }
+
+    Ok(())
+}
+
+fn polygon(coordinates: &Value, rings: &mut Vec<(Vec<[f64; 2]>, bool)>) -> io::Result<()> {
+    for (i, ring) in array(coordinates)?.iter().enumerate() {
+        let ring = array(ring)?
+            .iter()
+            .map(|p| match array(p)? {
+                [x, y, ..] => match (x.as_f64(), y.as_f64()) {
+                    (Some(x), Some(y)) => Ok([x, y]),
+                    _ => Err(invalid("invalid GeoJSON position")),
+                },
+                _ => Err(invalid("invalid GeoJSON position")),
+            })
+            .collect::<io::Result<_>>()?;
+
+        rings.push((ring, i == 0));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feature_collection() {
+        let json = br#"{
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": {"name": "\u00d8y \"A\"", "area": 4.0, "lake": false},
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [
+                            [[0, 0], [2, 0], [2, 2], [0, 2], [0, 0]],
+                            [[0.5, 0.5], [0.5, 1.5], [1.5, 1.5], [0.5, 0.5]]
+                        ]
+                    }
+                },
+                {"type": "Feature", "properties": null, "geometry": null},
+                {
+                    "type": "Feature",
+                    "properties": {},
+                    "geometry": {
+                        "type": "MultiPolygon",
+                        "coordinates": [[[[5, 5, 10], [6, 5, 10], [6, 6, 10], [5, 5, 10]]]]
+                    }
+                },
+                {"type": "Feature", "geometry": {"type": "Point", "coordinates": [1e1, -2.5E-1]}},
+                {"type": "\u0046eature", "geometry": {"type": "Polygon", "coordinates": []}},
+                {"type": "Feature", "properties": {"name": "\ud83c\udfdd"}, "geometry": null}
+            ]
+        }"#;
+
+        assert_eq!(
+            read_rings(json).unwrap(),
+            vec![
+                (vec![[0., 0.], [2., 0.], [2., 2.], [0., 2.], [0., 0.]], true),
+                (vec![[0.5, 0.5], [0.5, 1.5], [1.5, 1.5], [0.5, 0.5]], false),
+                (vec![[5., 5.], [6., 5.], [6., 6.], [5., 5.]], true),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_invalid_geojson() {
+        assert!(read_rings(b"").is_err());
+        assert!(read_rings(b"{\"type\": \"Polygon\"}").is_err());
+        assert!(read_rings(b"{\"type\": \"Polygon\", \"coordinates\": [[[0, 0]]]").is_err());
+        assert!(read_rings(b"{\"type\": \"Polygon\", \"coordinates\": [[[0]]]}").is_err());
+        assert!(read_rings(b"{\"type\": \"Polygon\", \"coordinates\": []} x").is_err());
+
+        // Deeply nested arrays are an error, not a stack overflow.
+        let deep = [vec![b'['; 100_000], vec![b']'; 100_000]].concat();
+        assert!(read_rings(&deep).is_err());
+    }
+}
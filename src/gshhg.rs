@@ -0,0 +1,334 @@
+//! Reading the native binary files of the GSHHG shoreline database, e.g. `gshhs_f.b`.
+//!
+//! Each polygon has a big-endian header followed by its points in micro-degrees. The polygons
+//! are kept with their level, area and source, and `rings` turns them into the rings of land
+//! polygons for `Shapes::from_rings` and `RoaringMask::from_rings`.
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io;
+
+/// Size of the header of a polygon in bytes.
+const HEADER: usize = 44;
+
+/// Where the shoreline of a polygon comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    /// CIA World Data Bank II.
+    Wdbii,
+    /// World Vector Shorelines.
+    Wvs,
+}
+
+/// A polygon in a GSHHG file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polygon {
+    /// Unique id of the polygon.
+    pub id: i32,
+    /// 1 for land, 2 for lakes, 3 for islands in lakes, 4 for ponds on islands in lakes, 5 for
+    /// the Antarctic ice front and 6 for the Antarctic grounding line.
+    pub level: u8,
+    /// Version of the GSHHG release.
+    pub version: u8,
+    pub source: Source,
+    /// The lake is a river-lake.
+    pub river: bool,
+    /// Area of the polygon at the resolution of the file (km^2).
+    pub area: f64,
+    /// Area of the full resolution polygon (km^2).
+    pub area_full: f64,
+    /// Id of the polygon that encloses this polygon.
+    pub container: Option<i32>,
+    /// Id of the full resolution polygon this polygon was made from.
+    pub ancestor: Option<i32>,
+    /// The points of the closed ring, as longitude and latitude. The longitudes are continuous
+    /// along the ring, so polygons crossing the antimeridian go past 180.
+    pub points: Vec<[f64; 2]>,
+}
+
+impl Polygon {
+    /// Whether the inside of the polygon is land: land, islands in lakes and Antarctica.
+    pub fn is_land(&self) -> bool {
+        self.level % 2 == 1 || self.level == 6
+    }
+}
+
+/// Read all the polygons in a GSHHG file.
+pub fn read_polygons(buf: &[u8]) -> io::Result<Vec<Polygon>> {
+    let mut polygons = Vec::new();
+    let mut pos = 0;
+
+    while pos < buf.len() {
+        let header = buf
+            .get(pos..pos + HEADER)
+            .ok_or_else(|| invalid("unexpected end of GSHHG header"))?;
+        let h = |i: usize| i32::from_be_bytes(header[4 * i..4 * i + 4].try_into().unwrap());
+        pos += HEADER;
+
+        let (id, n, flag, west) = (h(0), h(1), h(2), h(3));
+        if n < 0 {
+            return Err(invalid("invalid number of points in GSHHG polygon"));
+        }
+
+        let points = buf
+            .get(pos..pos.saturating_add(8 * n as usize))
+            .ok_or_else(|| invalid("unexpected end of GSHHG points"))?;
+        pos += points.len();
+
+        let greenwich = (flag >> 16) & 3 != 0;
+        let points = points
+            .chunks_exact(8)
+            .map(|p| {
+                let x = i32::from_be_bytes(p[..4].try_into().unwrap());
+                let y = i32::from_be_bytes(p[4..].try_into().unwrap());
+
+                // Like `gshhg.c`: the longitudes are in [0, 360], and are moved west of
+                // Greenwich for polygons that cross it or are entirely in the west.
+                let mut lon = x as f64 * 1e-6;
+                if (greenwich && x > 270_000_000) || west > 180_000_000 {
+                    lon -= 360.;
+                }
+
+                [lon, y as f64 * 1e-6]
+            })
+            .collect();
+
+        let scale = 10f64.powi((flag >> 26) & 255);
+        let id_or_none = |i: i32| (i >= 0).then_some(i);
+
+        polygons.push(Polygon {
+            id,
+            level: (flag & 255) as u8,
+            version: ((flag >> 8) & 255) as u8,
+            source: if (flag >> 24) & 1 == 1 {
+                Source::Wvs
+            } else {
+                Source::Wdbii
+            },
+            river: (flag >> 25) & 1 == 1,
+            area: h(7) as f64 / scale,
+            area_full: h(8) as f64 / scale,
+            container: id_or_none(h(9)),
+            ancestor: id_or_none(h(10)),
+            points,
+        });
+    }
+
+    Ok(polygons)
+}
+
+/// The rings of the land polygons, along with whether the ring is an exterior ring, like for
+/// `wkb::read_rings`. The lakes, and the levels within them, are left out unless
+/// `lakes_as_water`. The lakes become holes in the polygon that contains them.
+///
+/// Antarctica is given both by its ice front (level 5) and by its grounding line (level 6), the
+/// ice front is used like for the GSHHG shapefiles.
+///
+/// The rings crossing the antimeridian are cut along it, so that all the rings are within
+/// [-180, 180].
+pub fn rings(polygons: &[Polygon], lakes_as_water: bool) -> Vec<(Vec<[f64; 2]>, bool)> {
+    let wanted = |p: &Polygon| match p.level {
+        1 | 5 => true,
+        2..=4 => lakes_as_water,
+        _ => false,
+    };
+
+    let mut holes: HashMap<i32, Vec<&Polygon>> = HashMap::new();
+    for h in polygons.iter().filter(|h| wanted(h) && !h.is_land()) {
+        if let Some(container) = h.container {
+            holes.entry(container).or_default().push(h);
+        }
+    }
+
+    let mut rings = Vec::new();
+
+    for p in polygons.iter().filter(|p| wanted(p) && p.is_land()) {
+        for (west, part) in cut_antimeridian(&p.points) {
+            rings.push((part, true));
+
+            for h in holes.get(&p.id).into_iter().flatten() {
+                rings.extend(
+                    cut_antimeridian(&h.points)
+                        .into_iter()
+                        .filter(|(w, _)| *w == west)
+                        .map(|(_, hole)| (hole, false)),
+                );
+            }
+        }
+    }
+
+    rings
+}
+
+/// Cut the ring along the antimeridian, and move the part east of it to the western
+/// hemisphere. The parts are given along with whether they are west of the antimeridian.
+fn cut_antimeridian(ring: &[[f64; 2]]) -> Vec<(bool, Vec<[f64; 2]>)> {
+    if ring.iter().all(|p| p[0] <= 180.) {
+        return vec![(true, ring.to_vec())];
+    }
+
+    let mut parts = Vec::new();
+
+    for west in [true, false] {
+        let mut part = clip(ring, west);
+        if !west {
+            part.iter_mut().for_each(|p| p[0] -= 360.);
+        }
+
+        if part.len() >= 4 {
+            parts.push((west, part));
+        }
+    }
+
+    parts
+}
+
+/// Clip the closed ring to the part west, or east, of the antimeridian.
+fn clip(ring: &[[f64; 2]], west: bool) -> Vec<[f64; 2]> {
+    let inside = |p: &[f64; 2]| if west { p[0] <= 180. } else { p[0] >= 180. };
+    let mut part = Vec::new();
+
+    for e in ring.windows(2) {
+        let (a, b) = (e[0], e[1]);
+
+        if inside(&a) {
+            part.push(a);
+        }
+
+        if inside(&a) != inside(&b) && a[0] != 180. && b[0] != 180. {
+            let y = a[1] + (b[1] - a[1]) * (180. - a[0]) / (b[0] - a[0]);
+            part.push([180., y]);
+        }
+    }
+
+    if let Some(first) = part.first().copied() {
+        part.push(first);
+    }
+
+    part
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A GSHHG polygon with the header fields that matter for the tests.
+    fn polygon(id: i32, flag: i32, west: i32, container: i32, points: &[[i32; 2]]) -> Vec<u8> {
+        let header = [
+            id,
+            points.len() as i32,
+            flag,
+            west,
+            0,
+            0,
+            0,
+            1234,
+            5678,
+            container,
+            -1,
+        ];
+
+        let mut buf = Vec::new();
+        for v in header {
+            buf.extend_from_slice(&v.to_be_bytes());
+        }
+        for p in points {
+            buf.extend_from_slice(&p[0].to_be_bytes());
+            buf.extend_from_slice(&p[1].to_be_bytes());
+        }
+
+        buf
+    }
+
+    fn square(x0: i32, y0: i32, d: i32) -> Vec<[i32; 2]> {
+        vec![
+            [x0, y0],
+            [x0 + d, y0],
+            [x0 + d, y0 + d],
+            [x0, y0 + d],
+            [x0, y0],
+        ]
+    }
+
+    #[test]
+    fn test_read() {
+        let deg = 1_000_000;
+        let version = 12 << 8;
+
+        let mut buf = polygon(
+            0,
+            1 | version | 1 << 24 | 1 << 26,
+            0,
+            -1,
+            &square(0, 0, 10 * deg),
+        );
+        buf.extend(polygon(
+            1,
+            2 | version | 1 << 25,
+            deg,
+            0,
+            &square(deg, deg, deg),
+        ));
+        // Entirely in the western hemisphere.
+        buf.extend(polygon(
+            2,
+            1 | version,
+            300 * deg,
+            -1,
+            &square(300 * deg, 0, deg),
+        ));
+
+        let polygons = read_polygons(&buf).unwrap();
+        assert_eq!(polygons.len(), 3);
+
+        let land = &polygons[0];
+        assert_eq!((land.id, land.level, land.version), (0, 1, 12));
+        assert_eq!(land.source, Source::Wvs);
+        assert!(!land.river && land.is_land());
+        assert_eq!((land.area, land.area_full), (123.4, 567.8));
+        assert_eq!((land.container, land.ancestor), (None, None));
+        assert_eq!(land.points[2], [10., 10.]);
+
+        let lake = &polygons[1];
+        assert_eq!(lake.source, Source::Wdbii);
+        assert!(lake.river && !lake.is_land());
+        assert_eq!(lake.container, Some(0));
+
+        assert_eq!(polygons[2].points[0], [-60., 0.]);
+
+        assert_eq!(rings(&polygons, false).len(), 2);
+
+        let r = rings(&polygons, true);
+        assert_eq!(r.len(), 3);
+        assert!(r[0].1 && !r[1].1 && r[2].1);
+        assert_eq!(r[1].0[0], [1., 1.]);
+
+        assert!(read_polygons(&buf[..buf.len() - 1]).is_err());
+        assert!(read_polygons(&buf[..20]).is_err());
+    }
+
+    #[test]
+    fn test_antimeridian() {
+        let deg = 1_000_000;
+        let buf = polygon(0, 1, 170 * deg, -1, &square(170 * deg, 0, 20 * deg));
+        let polygons = read_polygons(&buf).unwrap();
+
+        let r = rings(&polygons, false);
+        assert_eq!(r.len(), 2);
+        for (ring, exterior) in &r {
+            assert!(exterior);
+            assert!(ring.iter().all(|p| (-180. ..=180.).contains(&p[0])));
+            assert_eq!(ring.first(), ring.last());
+        }
+
+        let mask =
+            crate::RoaringMask::from_rings(r, 1. / 240., crate::RasterRule::AnyLand).unwrap();
+        assert!(mask.contains(175., 5.));
+        assert!(mask.contains(-175., 5.));
+        assert!(!mask.contains(165., 5.));
+        assert!(!mask.contains(-165., 5.));
+    }
+}
@@ -0,0 +1,173 @@
+//! The GSHHG hierarchy of land, lakes, islands in lakes and ponds on islands in lakes.
+//!
+//! The embedded landmasks flatten the hierarchy into land and ocean. The levels are given as
+//! separate shapes, e.g. made from the `GSHHS_f_L1` to `GSHHS_f_L4` shapefiles, where the
+//! polygons of each level are inside the polygons of the level above.
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use std::path::Path;
+#[cfg(feature = "python")]
+use std::path::PathBuf;
+
+use crate::error::{check_coordinates, LandmaskError};
+use crate::Shapes;
+
+/// Level in the GSHHG hierarchy.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+    Ocean,
+    Land,
+    Lake,
+    IslandInLake,
+    PondOnIsland,
+}
+
+impl Level {
+    fn from_depth(depth: usize) -> Level {
+        match depth {
+            0 => Level::Ocean,
+            1 => Level::Land,
+            2 => Level::Lake,
+            3 => Level::IslandInLake,
+            _ => Level::PondOnIsland,
+        }
+    }
+
+    /// Whether the level is dry land: land or an island in a lake.
+    pub fn is_land(&self) -> bool {
+        matches!(self, Level::Land | Level::IslandInLake)
+    }
+}
+
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone)]
+pub struct Levels {
+    shapes: Vec<Shapes>,
+}
+
+impl Levels {
+    /// Make the levels from the shapes of each level, starting with land (L1).
+    pub fn from_shapes(shapes: Vec<Shapes>) -> Levels {
+        assert!(!shapes.is_empty() && shapes.len() <= 4);
+
+        Levels { shapes }
+    }
+
+    /// Load the levels from xz-compressed WKB files, in order: land (L1), lakes (L2), islands in
+    /// lakes (L3) and ponds on islands in lakes (L4). The lower levels may be left out.
+    pub fn from_compressed<P: AsRef<Path>>(paths: Vec<P>) -> Result<Levels, LandmaskError> {
+        if paths.is_empty() || paths.len() > 4 {
+            return Err(LandmaskError::InvalidInput(
+                "expected between one and four levels".into(),
+            ));
+        }
+
+        let shapes = paths
+            .iter()
+            .map(Shapes::from_compressed)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Levels::from_shapes(shapes))
+    }
+
+    /// Level of point (x, y), assuming that it is on land (L1).
+    pub(crate) fn inland_level_unchecked(&self, x: f64, y: f64) -> Level {
+        let depth = 1 + self.shapes[1..]
+            .iter()
+            .take_while(|s| s.contains_unchecked(x, y))
+            .count();
+
+        Level::from_depth(depth)
+    }
+
+    /// Level of point (x, y).
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// Panics if `x` is NaN or infinite, or if `y` is outside [-90, 90].
+    pub fn level(&self, x: f64, y: f64) -> Level {
+        assert!(x.is_finite());
+        assert!(y >= -90. && y <= 90.);
+
+        self.level_unchecked(super::modulate_longitude(x), y)
+    }
+
+    /// Same as `level`, but returns an error for NaN or infinite coordinates and latitudes
+    /// outside [-90, 90] instead of panicking.
+    pub fn try_level(&self, x: f64, y: f64) -> Result<Level, LandmaskError> {
+        check_coordinates(x, y)?;
+
+        Ok(self.level_unchecked(super::modulate_longitude(x), y))
+    }
+
+    /// Same as `level`, but does not check for bounds.
+    fn level_unchecked(&self, x: f64, y: f64) -> Level {
+        if self.shapes[0].contains_unchecked(x, y) {
+            self.inland_level_unchecked(x, y)
+        } else {
+            Level::Ocean
+        }
+    }
+}
+
+#[cfg_attr(feature = "python", pymethods)]
+impl Levels {
+    #[cfg(feature = "python")]
+    #[pyo3(name = "level")]
+    fn py_level(&self, x: f64, y: f64) -> Result<Level, LandmaskError> {
+        self.try_level(x, y)
+    }
+
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "from_compressed")]
+    fn py_from_compressed(paths: Vec<PathBuf>) -> Result<Levels, LandmaskError> {
+        Levels::from_compressed(paths)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn square(x0: f64, x1: f64) -> Shapes {
+        let ring = vec![[x0, x0], [x1, x0], [x1, x1], [x0, x1], [x0, x0]];
+        Shapes::from_wkb(&crate::wkb::write_rings(&[(ring, true)])).unwrap()
+    }
+
+    #[test]
+    fn test_nested_levels() {
+        let levels = Levels::from_shapes(vec![
+            square(0., 10.),
+            square(2., 8.),
+            square(3., 7.),
+            square(4., 6.),
+        ]);
+
+        assert_eq!(levels.level(-1., -1.), Level::Ocean);
+        assert_eq!(levels.level(1., 1.), Level::Land);
+        assert_eq!(levels.level(2.5, 2.5), Level::Lake);
+        assert_eq!(levels.level(3.5, 3.5), Level::IslandInLake);
+        assert_eq!(levels.level(5., 5.), Level::PondOnIsland);
+        assert_eq!(levels.level(365., 5.), Level::PondOnIsland);
+
+        assert!(Level::IslandInLake.is_land());
+        assert!(!Level::Lake.is_land());
+    }
+
+    #[test]
+    fn test_land_only() {
+        let levels = Levels::from_shapes(vec![square(0., 10.)]);
+        assert_eq!(levels.level(5., 5.), Level::Land);
+        assert_eq!(levels.level(5., -90.), Level::Ocean);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_level_nan() {
+        let levels = Levels::from_shapes(vec![square(0., 10.)]);
+        levels.level(f64::NAN, 5.);
+    }
+}
@@ -22,11 +22,9 @@
 //! # use std::io;
 //! # fn main() -> io::Result<()> {
 //! #
-//! # pyo3::prepare_freethreaded_python();
-//! # pyo3::Python::with_gil(|py| {
 //! use roaring_landmask::RoaringLandmask;
 //!
-//! let mask = RoaringLandmask::new(py).unwrap();
+//! let mask = RoaringLandmask::new()?;
 //!
 //! // Check some points on land
 //! assert!(mask.contains(15., 65.6));
@@ -34,7 +32,14 @@
 //!
 //! // Check a point in the ocean
 //! assert!(!mask.contains(5., 65.6));
-//! # });
+//!
+//! // Check many points at once, see `ContainsMany`
+//! use roaring_landmask::ContainsMany;
+//!
+//! let (x, y) = ([15., 5.], [65.6, 65.6]);
+//! let mut on_land = [false; 2];
+//! mask.contains_slice(&x, &y, &mut on_land);
+//! assert_eq!(on_land, [true, false]);
 //! #
 //! # Ok(())
 //! # }
@@ -59,72 +64,178 @@
 #[cfg(feature = "nightly")]
 extern crate test;
 
-#[macro_use]
-extern crate lazy_static;
-
 // geos-sys needs libc++, probably libstdc++. On Windows Conda builds this hopefully adds the
 // correct flags to the linker.
 // extern crate link_cplusplus;
 
-use numpy::{PyArray, PyReadonlyArrayDyn};
+#[cfg(feature = "python")]
+use batch::PyCoords;
+use ndarray::{Array2, ArrayView2};
+#[cfg(feature = "python")]
+use numpy::{PyArray, PyArrayDyn, PyReadonlyArray2, PyReadonlyArrayDyn};
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
-use std::io;
 
+pub mod batch;
+pub mod coast;
+pub mod error;
+#[cfg(feature = "cli")]
+pub mod geojson;
+pub mod gshhg;
+pub mod levels;
+pub mod longitude;
 pub mod mask;
 pub mod providers;
+pub mod raster;
+pub mod shapefile;
 pub mod shapes;
+pub mod wkb;
 
-pub use mask::RoaringMask;
+pub use batch::ContainsMany;
+pub use coast::Nearest;
+pub use error::{InvalidPolicy, LandmaskError};
+pub use levels::{Level, Levels};
+use longitude::modulate_longitude;
+pub use longitude::LongitudeConvention;
+pub use mask::{CellClass, Grid, MaskMetadata, RoaringMask};
 pub use providers::LandmaskProvider;
+pub use raster::RasterRule;
 pub use shapes::Shapes;
 
 include!(concat!(env!("OUT_DIR"), "/source_data.rs"));
 
+/// Largest distance (cells of the landmask) searched for ocean points.
+const OCEAN_SEARCH_CELLS: i64 = 240;
+
+#[cfg(feature = "python")]
 #[pymodule]
 fn roaring_landmask(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<mask::Affine>()?;
+    m.add_class::<mask::Grid>()?;
+    m.add_class::<mask::MaskMetadata>()?;
     m.add_class::<RoaringMask>()?;
+    m.add_class::<CellClass>()?;
     m.add_class::<Shapes>()?;
     m.add_class::<RoaringLandmask>()?;
+    m.add_class::<InvalidPolicy>()?;
+    m.add_class::<LongitudeConvention>()?;
+    m.add(
+        "InvalidCoordinateError",
+        _py.get_type::<error::InvalidCoordinateError>(),
+    )?;
+    m.add(
+        "InvalidDataError",
+        _py.get_type::<error::InvalidDataError>(),
+    )?;
+    m.add("GeosError", _py.get_type::<error::GeosError>())?;
     m.add_class::<LandmaskProvider>()?;
+    m.add_class::<coast::Nearest>()?;
+    m.add_class::<RasterRule>()?;
+    m.add_class::<Level>()?;
+    m.add_class::<Levels>()?;
 
     Ok(())
 }
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 pub struct RoaringLandmask {
-    #[pyo3(get)]
     pub mask: RoaringMask,
-    #[pyo3(get)]
     pub shapes: Shapes,
+    /// The GSHHG levels, if given.
+    levels: Option<Levels>,
+    /// Whether `contains` treats lakes as water, using the levels.
+    lakes_as_water: bool,
+    /// The range of the longitudes given to the point queries.
+    longitudes: LongitudeConvention,
 }
 
-#[pymethods]
 impl RoaringLandmask {
-    #[staticmethod]
-    pub fn new(py: Python) -> io::Result<RoaringLandmask> {
-        Self::new_with_provider(py, LandmaskProvider::Gshhg)
+    pub fn new() -> Result<RoaringLandmask, LandmaskError> {
+        Self::new_with_provider(LandmaskProvider::Gshhg)
     }
 
-    #[staticmethod]
     pub fn new_with_provider(
-        py: Python,
         landmask_provider: LandmaskProvider,
-    ) -> io::Result<RoaringLandmask> {
+    ) -> Result<RoaringLandmask, LandmaskError> {
         let mask = RoaringMask::new(landmask_provider)?;
-        let shapes = Shapes::new(py, landmask_provider)?;
+        let shapes = Shapes::new(landmask_provider)?;
 
-        Ok(RoaringLandmask { mask, shapes })
+        Ok(RoaringLandmask {
+            mask,
+            shapes,
+            levels: None,
+            lakes_as_water: false,
+            longitudes: LongitudeConvention::Any,
+        })
     }
 
-    #[getter]
-    pub fn dx(&self) -> f64 {
-        self.mask.dx()
+    /// Make a landmask that uses the GSHHG levels, so that `level` gives the level of the
+    /// points. With `lakes_as_water` lakes are water (and islands in lakes land) in `contains`,
+    /// otherwise lakes are land like in the embedded landmask. The coastline queries only use
+    /// the land polygons.
+    pub fn new_with_levels(
+        landmask_provider: LandmaskProvider,
+        levels: Levels,
+        lakes_as_water: bool,
+    ) -> Result<RoaringLandmask, LandmaskError> {
+        let mut mask = Self::new_with_provider(landmask_provider)?;
+        mask.levels = Some(levels);
+        mask.lakes_as_water = lakes_as_water;
+
+        Ok(mask)
     }
 
-    #[getter]
-    pub fn dy(&self) -> f64 {
-        self.mask.dy()
+    /// Whether lakes are water in `contains`, see `new_with_levels`.
+    pub fn lakes_as_water(&self) -> bool {
+        self.lakes_as_water
+    }
+
+    /// Use the longitude convention for the point and segment queries, e.g. `contains`,
+    /// `intersects_segment` and `distance_to_coast`. Longitudes outside the convention panic, or
+    /// are errors in the `try_` queries. By default any longitude is wrapped around the Earth.
+    ///
+    /// The longitudes returned by the queries are always in [-180, 180).
+    pub fn with_longitude_convention(mut self, longitudes: LongitudeConvention) -> Self {
+        self.longitudes = longitudes;
+        self
+    }
+
+    /// The longitude convention of the point and segment queries, see
+    /// `with_longitude_convention`.
+    pub fn longitude_convention(&self) -> LongitudeConvention {
+        self.longitudes
+    }
+
+    /// Find the coastal and interior cells of the mask now. Points in the interior land cells
+    /// do not need to be checked against the shapes, which makes `contains` much faster inland.
+    /// Finding the cells takes a little while, without them every land cell is checked against
+    /// the shapes and is `Coastal` in `classify`.
+    pub fn with_interior_cells(self) -> Self {
+        self.mask.init_coastal(self.shapes.coastline());
+        self
+    }
+
+    /// Check if point (x, y) is in an interior land cell, does not check for bounds.
+    fn interior_unchecked(&self, x: f64, y: f64) -> bool {
+        self.mask.interior_unchecked(x, y)
+    }
+
+    /// The longitude `x` wrapped onto [-180, 180), panicking if it is outside the convention.
+    fn longitude(&self, x: f64) -> f64 {
+        self.longitudes
+            .normalize(x)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Check that all the points are valid for the `try_` queries.
+    fn check_points<I>(&self, points: I) -> Result<(), LandmaskError>
+    where
+        I: IntoIterator<Item = (f64, f64)>,
+    {
+        points.into_iter().try_for_each(|(x, y)| {
+            error::check_coordinates(x, y)?;
+            self.longitudes.normalize(x).map(|_| ())
+        })
     }
 
     /// Check if point (x, y) is on land.
@@ -133,186 +244,1668 @@ impl RoaringLandmask {
     /// `y` is latitude,  [- 90,  90] north
     ///
     ///
-    /// Returns `true` if the point is on land or close to the shore.
+    /// Returns `true` if the point is on land or close to the shore. Points in land cells are
+    /// checked against the shapes, unless they are interior cells, see `with_interior_cells`.
     pub fn contains(&self, x: f64, y: f64) -> bool {
         assert!(y >= -90. && y <= 90.);
 
-        let x = modulate_longitude(x);
+        self.contains_unchecked(self.longitude(x), y)
+    }
+
+    /// Same as `contains`, but does not check for bounds. `x` must be in [-180, 180).
+    fn contains_unchecked(&self, x: f64, y: f64) -> bool {
+        self.mask.contains_unchecked(x, y)
+            && (self.interior_unchecked(x, y) || self.shapes.contains_unchecked(x, y))
+            && (!self.lakes_as_water
+                || self
+                    .levels
+                    .as_ref()
+                    .map_or(true, |l| l.inland_level_unchecked(x, y).is_land()))
+    }
+
+    /// Same as `contains`, but returns an error for NaN or infinite coordinates, latitudes
+    /// outside [-90, 90] and longitudes outside the longitude convention instead of panicking.
+    pub fn try_contains(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        error::check_coordinates(x, y)?;
+        self.longitudes.normalize(x)?;
+
+        Ok(self.contains(x, y))
+    }
+
+    pub fn dx(&self) -> f64 {
+        self.mask.dx()
+    }
+
+    pub fn dy(&self) -> f64 {
+        self.mask.dy()
+    }
+}
+
+#[cfg_attr(feature = "python", pymethods)]
+impl RoaringLandmask {
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "new")]
+    fn py_new() -> Result<RoaringLandmask, LandmaskError> {
+        RoaringLandmask::new()
+    }
+
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "new_with_provider")]
+    #[pyo3(signature = (landmask_provider, interior_cells = false))]
+    fn py_new_with_provider(
+        landmask_provider: LandmaskProvider,
+        interior_cells: bool,
+    ) -> Result<RoaringLandmask, LandmaskError> {
+        let mask = RoaringLandmask::new_with_provider(landmask_provider)?;
+
+        Ok(if interior_cells {
+            mask.with_interior_cells()
+        } else {
+            mask
+        })
+    }
+
+    /// Make a landmask that uses the GSHHG levels, see `new_with_levels`.
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "new_with_levels")]
+    fn py_new_with_levels(
+        landmask_provider: LandmaskProvider,
+        levels: Levels,
+        lakes_as_water: bool,
+    ) -> Result<RoaringLandmask, LandmaskError> {
+        RoaringLandmask::new_with_levels(landmask_provider, levels, lakes_as_water)
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "mask")]
+    fn py_mask(&self) -> RoaringMask {
+        self.mask.clone()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "shapes")]
+    fn py_shapes(&self) -> Shapes {
+        self.shapes.clone()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "lakes_as_water")]
+    fn py_lakes_as_water(&self) -> bool {
+        self.lakes_as_water
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "longitude_convention")]
+    fn py_longitude_convention(&self) -> LongitudeConvention {
+        self.longitudes
+    }
+
+    #[cfg(feature = "python")]
+    #[setter]
+    #[pyo3(name = "longitude_convention")]
+    fn py_set_longitude_convention(&mut self, longitudes: LongitudeConvention) {
+        self.longitudes = longitudes;
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "dx")]
+    fn py_dx(&self) -> f64 {
+        self.dx()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "dy")]
+    fn py_dy(&self) -> f64 {
+        self.dy()
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "contains")]
+    fn py_contains(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        self.try_contains(x, y)
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "level")]
+    fn py_level(&self, x: f64, y: f64) -> Result<Level, LandmaskError> {
+        self.try_level(x, y)
+    }
 
-        self.mask.contains_unchecked(x, y) && self.shapes.contains_unchecked(x, y)
+    #[cfg(feature = "python")]
+    #[pyo3(name = "classify")]
+    fn py_classify(&self, x: f64, y: f64) -> Result<CellClass, LandmaskError> {
+        self.try_classify(x, y)
     }
 
-    fn contains_many(
+    #[cfg(feature = "python")]
+    /// Classify many points, see `classify`. The classes are given as integers: `0` is ocean,
+    /// `1` is land and `2` is coastal.
+    pub fn classify_many(
         &self,
         py: Python,
         x: PyReadonlyArrayDyn<f64>,
         y: PyReadonlyArrayDyn<f64>,
-    ) -> Py<PyArray<bool, numpy::Ix1>> {
+    ) -> Result<Py<PyArray<u8, numpy::Ix1>>, LandmaskError> {
         let x = x.as_array();
         let y = y.as_array();
 
-        PyArray::from_iter(
+        self.check_points(x.iter().copied().zip(y.iter().copied()))?;
+
+        Ok(PyArray::from_iter(
             py,
-            x.iter().zip(y.iter()).map(|(x, y)| self.contains(*x, *y)),
+            x.iter()
+                .zip(y.iter())
+                .map(|(x, y)| self.classify(*x, *y) as u8),
         )
-        .to_owned()
+        .to_owned())
+    }
+
+    /// Check many points, see `contains`. Invalid points raise an error, or are treated as ocean
+    /// or land, depending on `invalid`.
+    ///
+    /// The coordinates may be float64, float32, int64 or int32 arrays, and are multiplied by
+    /// `scale`, e.g. `1e-7` for integers in units of 1e-7 degrees.
+    ///
+    /// The results are written to `out` if it is given, which must have the same size as `x`.
+    /// The GIL is released while the points are checked.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, invalid = InvalidPolicy::Raise, scale = 1., out = None))]
+    pub fn contains_many(
+        &self,
+        py: Python,
+        x: PyCoords,
+        y: PyCoords,
+        invalid: InvalidPolicy,
+        scale: f64,
+        out: Option<&PyArrayDyn<bool>>,
+    ) -> Result<PyObject, LandmaskError> {
+        crate::batch::contains_many_py(self, py, x, y, out, scale, invalid)
+    }
+
+    /// Check many points, giving `1` for land, `0` for ocean and `-1` for invalid points, e.g. NaN
+    /// or latitudes outside [-90, 90]. The result has the same shape as `x` and `y`.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, scale = 1.))]
+    pub fn contains_many_i8(
+        &self,
+        py: Python,
+        x: PyCoords,
+        y: PyCoords,
+        scale: f64,
+    ) -> Result<Py<PyArray<i8, numpy::IxDyn>>, LandmaskError> {
+        crate::batch::contains_many_i8_py(self, py, x, y, scale)
     }
 
+    /// Check many points in parallel, see `contains_many`.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, invalid = InvalidPolicy::Raise, scale = 1., out = None))]
     pub fn contains_many_par(
+        &self,
+        py: Python,
+        x: PyCoords,
+        y: PyCoords,
+        invalid: InvalidPolicy,
+        scale: f64,
+        out: Option<&PyArrayDyn<bool>>,
+    ) -> Result<PyObject, LandmaskError> {
+        crate::batch::contains_many_par_py(self, py, x, y, out, scale, invalid)
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "intersects_segment")]
+    fn py_intersects_segment(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<bool, LandmaskError> {
+        self.try_intersects_segment(x0, y0, x1, y1)
+    }
+
+    #[cfg(feature = "python")]
+    fn intersects_segment_many(
+        &self,
+        py: Python,
+        x0: PyReadonlyArrayDyn<f64>,
+        y0: PyReadonlyArrayDyn<f64>,
+        x1: PyReadonlyArrayDyn<f64>,
+        y1: PyReadonlyArrayDyn<f64>,
+    ) -> Result<Py<PyArray<bool, numpy::Ix1>>, LandmaskError> {
+        let x0 = x0.as_array();
+        let y0 = y0.as_array();
+        let x1 = x1.as_array();
+        let y1 = y1.as_array();
+
+        self.check_points(
+            (x0.iter().copied().zip(y0.iter().copied()))
+                .chain(x1.iter().copied().zip(y1.iter().copied())),
+        )?;
+
+        Ok(PyArray::from_iter(
+            py,
+            x0.iter()
+                .zip(y0.iter())
+                .zip(x1.iter().zip(y1.iter()))
+                .map(|((x0, y0), (x1, y1))| self.intersects_segment(*x0, *y0, *x1, *y1)),
+        )
+        .to_owned())
+    }
+
+    #[cfg(feature = "python")]
+    pub fn intersects_segment_many_par(
+        &self,
+        py: Python,
+        x0: PyReadonlyArrayDyn<f64>,
+        y0: PyReadonlyArrayDyn<f64>,
+        x1: PyReadonlyArrayDyn<f64>,
+        y1: PyReadonlyArrayDyn<f64>,
+    ) -> Result<Py<PyArray<bool, numpy::IxDyn>>, LandmaskError> {
+        let x0 = x0.as_array();
+        let y0 = y0.as_array();
+        let x1 = x1.as_array();
+        let y1 = y1.as_array();
+
+        self.check_points(
+            (x0.iter().copied().zip(y0.iter().copied()))
+                .chain(x1.iter().copied().zip(y1.iter().copied())),
+        )?;
+
+        use ndarray::Zip;
+        let intersects = Zip::from(&x0)
+            .and(&y0)
+            .and(&x1)
+            .and(&y1)
+            .par_map_collect(|x0, y0, x1, y1| self.intersects_segment(*x0, *y0, *x1, *y1));
+        Ok(PyArray::from_owned_array(py, intersects).to_owned())
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "first_land_hit")]
+    fn py_first_land_hit(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<Option<(f64, f64, f64)>, LandmaskError> {
+        self.try_first_land_hit(x0, y0, x1, y1)
+    }
+
+    #[cfg(feature = "python")]
+    /// Find the first point where each segment from (x0, y0) to (x1, y1) meets land.
+    ///
+    /// Returns the index of the segments that meet land, along with the longitude, latitude and
+    /// fraction of the segment travelled, as in `first_land_hit`.
+    #[allow(clippy::type_complexity)]
+    fn first_land_hit_many(
+        &self,
+        py: Python,
+        x0: PyReadonlyArrayDyn<f64>,
+        y0: PyReadonlyArrayDyn<f64>,
+        x1: PyReadonlyArrayDyn<f64>,
+        y1: PyReadonlyArrayDyn<f64>,
+    ) -> Result<
+        (
+            Py<PyArray<usize, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+        ),
+        LandmaskError,
+    > {
+        let x0 = x0.as_array();
+        let y0 = y0.as_array();
+        let x1 = x1.as_array();
+        let y1 = y1.as_array();
+
+        self.check_points(
+            (x0.iter().copied().zip(y0.iter().copied()))
+                .chain(x1.iter().copied().zip(y1.iter().copied())),
+        )?;
+
+        let hits = x0
+            .iter()
+            .zip(y0.iter())
+            .zip(x1.iter().zip(y1.iter()))
+            .map(|((x0, y0), (x1, y1))| self.first_land_hit(*x0, *y0, *x1, *y1));
+
+        Ok(hits_to_arrays(py, hits))
+    }
+
+    #[cfg(feature = "python")]
+    #[allow(clippy::type_complexity)]
+    pub fn first_land_hit_many_par(
+        &self,
+        py: Python,
+        x0: PyReadonlyArrayDyn<f64>,
+        y0: PyReadonlyArrayDyn<f64>,
+        x1: PyReadonlyArrayDyn<f64>,
+        y1: PyReadonlyArrayDyn<f64>,
+    ) -> Result<
+        (
+            Py<PyArray<usize, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+        ),
+        LandmaskError,
+    > {
+        let x0 = x0.as_array();
+        let y0 = y0.as_array();
+        let x1 = x1.as_array();
+        let y1 = y1.as_array();
+
+        self.check_points(
+            (x0.iter().copied().zip(y0.iter().copied()))
+                .chain(x1.iter().copied().zip(y1.iter().copied())),
+        )?;
+
+        use ndarray::Zip;
+        let hits = Zip::from(&x0)
+            .and(&y0)
+            .and(&x1)
+            .and(&y1)
+            .par_map_collect(|x0, y0, x1, y1| self.first_land_hit(*x0, *y0, *x1, *y1));
+
+        Ok(hits_to_arrays(py, hits.iter().copied()))
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "nearest_ocean_point")]
+    fn py_nearest_ocean_point(
+        &self,
+        x: f64,
+        y: f64,
+        min_offshore: f64,
+    ) -> Result<Option<(f64, f64)>, LandmaskError> {
+        self.try_nearest_ocean_point(x, y, min_offshore)
+    }
+
+    #[allow(clippy::type_complexity)]
+    #[cfg(feature = "python")]
+    /// Find the closest ocean point for each point (x, y), as in `nearest_ocean_point`.
+    ///
+    /// Returns arrays of longitude and latitude, which are NaN where no ocean point is found.
+    fn nearest_ocean_point_many(
         &self,
         py: Python,
         x: PyReadonlyArrayDyn<f64>,
         y: PyReadonlyArrayDyn<f64>,
-    ) -> Py<PyArray<bool, numpy::IxDyn>> {
+        min_offshore: f64,
+    ) -> Result<(Py<PyArray<f64, numpy::Ix1>>, Py<PyArray<f64, numpy::Ix1>>), LandmaskError> {
         let x = x.as_array();
         let y = y.as_array();
 
-        use ndarray::Zip;
-        let contains = Zip::from(&x)
-            .and(&y)
-            .par_map_collect(|x, y| self.contains(*x, *y));
-        PyArray::from_owned_array(py, contains).to_owned()
+        self.check_points(x.iter().copied().zip(y.iter().copied()))?;
+
+        let (ox, oy): (Vec<f64>, Vec<f64>) = x
+            .iter()
+            .zip(y.iter())
+            .map(|(x, y)| {
+                self.nearest_ocean_point(*x, *y, min_offshore)
+                    .unwrap_or((f64::NAN, f64::NAN))
+            })
+            .unzip();
+
+        Ok((
+            PyArray::from_vec(py, ox).to_owned(),
+            PyArray::from_vec(py, oy).to_owned(),
+        ))
     }
-}
 
-/// Move longitude into -180 to 180 domain.
-fn modulate_longitude(lon: f64) -> f64 {
-    ((lon + 180.) % 360.) - 180.
-}
+    #[allow(clippy::type_complexity)]
+    #[cfg(feature = "python")]
+    pub fn nearest_ocean_point_many_par(
+        &self,
+        py: Python,
+        x: PyReadonlyArrayDyn<f64>,
+        y: PyReadonlyArrayDyn<f64>,
+        min_offshore: f64,
+    ) -> Result<
+        (
+            Py<PyArray<f64, numpy::IxDyn>>,
+            Py<PyArray<f64, numpy::IxDyn>>,
+        ),
+        LandmaskError,
+    > {
+        let x = x.as_array();
+        let y = y.as_array();
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+        self.check_points(x.iter().copied().zip(y.iter().copied()))?;
 
-    #[test]
-    fn load_ms() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            let _ms = RoaringLandmask::new_with_provider(py, LandmaskProvider::Gshhg).unwrap();
-            let _ms = RoaringLandmask::new_with_provider(py, LandmaskProvider::Osm).unwrap();
-        })
+        use ndarray::Zip;
+        let ocean = Zip::from(&x).and(&y).par_map_collect(|x, y| {
+            self.nearest_ocean_point(*x, *y, min_offshore)
+                .unwrap_or((f64::NAN, f64::NAN))
+        });
+
+        Ok((
+            PyArray::from_owned_array(py, ocean.mapv(|(x, _)| x)).to_owned(),
+            PyArray::from_owned_array(py, ocean.mapv(|(_, y)| y)).to_owned(),
+        ))
     }
 
-    #[test]
-    fn test_np() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(!mask.contains(5., 90.));
+    #[cfg(feature = "python")]
+    /// Rasterize the landmask onto a regular grid, see `RoaringLandmask::rasterize`. Returns a
+    /// boolean array, or the fraction of land for `RasterRule::Fraction`.
+    #[pyo3(name = "rasterize")]
+    #[allow(clippy::too_many_arguments)]
+    fn py_rasterize(
+        &self,
+        py: Python,
+        lon_min: f64,
+        lon_max: f64,
+        nx: usize,
+        lat_min: f64,
+        lat_max: f64,
+        ny: usize,
+        rule: RasterRule,
+    ) -> Result<PyObject, LandmaskError> {
+        let raster = self.try_rasterize(lon_min, lon_max, nx, lat_min, lat_max, ny, rule)?;
 
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Osm).unwrap();
-            assert!(!mask.contains(5., 90.));
+        Ok(match rule {
+            RasterRule::Fraction => PyArray::from_owned_array(py, raster).to_owned().into_py(py),
+            _ => PyArray::from_owned_array(py, raster.mapv(|v| v > 0.5))
+                .to_owned()
+                .into_py(py),
         })
     }
 
-    #[test]
-    fn test_sp() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(mask.contains(5., -89.99));
+    #[cfg(feature = "python")]
+    /// Fraction of land in each cell of a curvilinear grid, see
+    /// `RoaringLandmask::curvilinear_land_fraction`.
+    #[pyo3(name = "curvilinear_land_fraction")]
+    fn py_curvilinear_land_fraction(
+        &self,
+        py: Python,
+        lon: PyReadonlyArray2<f64>,
+        lat: PyReadonlyArray2<f64>,
+    ) -> Result<Py<PyArray<f64, numpy::Ix2>>, LandmaskError> {
+        let fraction = self.try_curvilinear_land_fraction(lon.as_array(), lat.as_array())?;
+        Ok(PyArray::from_owned_array(py, fraction).to_owned())
+    }
 
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Osm).unwrap();
-            assert!(mask.contains(5., -89.99));
-        })
+    #[cfg(feature = "python")]
+    #[pyo3(name = "distance_to_coast")]
+    fn py_distance_to_coast(&self, x: f64, y: f64) -> Result<f64, LandmaskError> {
+        self.try_distance_to_coast(x, y)
     }
 
-    #[test]
-    #[should_panic]
-    fn test_sp_oob() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(mask.contains(5., -90.));
+    #[cfg(feature = "python")]
+    #[pyo3(name = "nearest_coast")]
+    fn py_nearest_coast(&self, x: f64, y: f64) -> Result<Option<Nearest>, LandmaskError> {
+        self.try_nearest_coast(x, y)
+    }
 
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Osm).unwrap();
-            assert!(mask.contains(5., -90.));
-        })
+    #[cfg(feature = "python")]
+    /// Find the nearest coastline point for each point (x, y).
+    ///
+    /// Returns arrays with the longitude, latitude, distance, bearing and eastward and northward
+    /// components of the normal, as in `Nearest`.
+    #[allow(clippy::type_complexity)]
+    fn nearest_coast_many(
+        &self,
+        py: Python,
+        x: PyReadonlyArrayDyn<f64>,
+        y: PyReadonlyArrayDyn<f64>,
+    ) -> Result<
+        (
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+            Py<PyArray<f64, numpy::Ix1>>,
+        ),
+        LandmaskError,
+    > {
+        let x = x.as_array();
+        let y = y.as_array();
+
+        self.check_points(x.iter().copied().zip(y.iter().copied()))?;
+
+        let nearest = x
+            .iter()
+            .zip(y.iter())
+            .map(|(x, y)| self.nearest_coast(*x, *y))
+            .collect::<Vec<_>>();
+
+        let field = |f: fn(&Nearest) -> f64| {
+            PyArray::from_iter(py, nearest.iter().map(|n| n.as_ref().map_or(f64::NAN, f)))
+                .to_owned()
+        };
+
+        Ok((
+            field(|n| n.x),
+            field(|n| n.y),
+            field(|n| n.distance),
+            field(|n| n.bearing),
+            field(|n| n.normal_x),
+            field(|n| n.normal_y),
+        ))
     }
 
-    #[test]
-    fn test_dateline_wrap() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                let mask = RoaringLandmask::new_with_provider(py, provider).unwrap();
-                // Close to NP
-                assert!(!mask.contains(5., 89.));
-                // Close to SP
-                assert!(mask.contains(5., -89.));
-                // Within bounds
-                let x = (-180..180).map(f64::from).collect::<Vec<_>>();
-                let m = x.iter().map(|x| mask.contains(*x, 65.)).collect::<Vec<_>>();
-                // Wrapped bounds
-                let x = (180..540).map(f64::from).collect::<Vec<_>>();
-                let mm = x.iter().map(|x| mask.contains(*x, 65.)).collect::<Vec<_>>();
-                assert_eq!(m, mm);
-            }
-        })
+    #[cfg(feature = "python")]
+    fn distance_to_coast_many(
+        &self,
+        py: Python,
+        x: PyReadonlyArrayDyn<f64>,
+        y: PyReadonlyArrayDyn<f64>,
+    ) -> Result<Py<PyArray<f64, numpy::Ix1>>, LandmaskError> {
+        let x = x.as_array();
+        let y = y.as_array();
+
+        self.check_points(x.iter().copied().zip(y.iter().copied()))?;
+
+        Ok(PyArray::from_iter(
+            py,
+            x.iter()
+                .zip(y.iter())
+                .map(|(x, y)| self.distance_to_coast(*x, *y)),
+        )
+        .to_owned())
     }
 
-    #[test]
-    #[should_panic]
-    fn test_not_on_earth_north() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(!mask.contains(5., 95.));
+    #[cfg(feature = "python")]
+    pub fn distance_to_coast_many_par(
+        &self,
+        py: Python,
+        x: PyReadonlyArrayDyn<f64>,
+        y: PyReadonlyArrayDyn<f64>,
+    ) -> Result<Py<PyArray<f64, numpy::IxDyn>>, LandmaskError> {
+        let x = x.as_array();
+        let y = y.as_array();
 
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Osm).unwrap();
-            assert!(!mask.contains(5., 95.));
-        })
+        self.check_points(x.iter().copied().zip(y.iter().copied()))?;
+
+        use ndarray::Zip;
+        let distance = Zip::from(&x)
+            .and(&y)
+            .par_map_collect(|x, y| self.distance_to_coast(*x, *y));
+        Ok(PyArray::from_owned_array(py, distance).to_owned())
     }
+}
 
-    #[test]
-    #[should_panic]
-    fn test_not_on_earth_south() {
-        pyo3::prepare_freethreaded_python();
-        pyo3::Python::with_gil(|py| {
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(!mask.contains(5., -95.));
+impl RoaringLandmask {
+    /// The GSHHG level of point (x, y). Without the levels only `Level::Ocean` and
+    /// `Level::Land` are known.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    pub fn level(&self, x: f64, y: f64) -> Level {
+        assert!(y >= -90. && y <= 90.);
 
-            let mask = RoaringLandmask::new_with_provider(py, LandmaskProvider::Osm).unwrap();
-            assert!(!mask.contains(5., -95.));
-        })
+        let x = self.longitude(x);
+
+        if !(self.mask.contains_unchecked(x, y) && self.shapes.contains_unchecked(x, y)) {
+            Level::Ocean
+        } else if let Some(levels) = &self.levels {
+            levels.inland_level_unchecked(x, y)
+        } else {
+            Level::Land
+        }
     }
 
-    #[cfg(feature = "nightly")]
-    mod benches {
-        use super::*;
-        use test::Bencher;
+    /// Same as `level`, but returns an error for invalid coordinates instead of panicking, see
+    /// `try_contains`.
+    pub fn try_level(&self, x: f64, y: f64) -> Result<Level, LandmaskError> {
+        error::check_coordinates(x, y)?;
+        self.longitudes.normalize(x)?;
 
-        #[bench]
-        fn test_contains_on_land(b: &mut Bencher) {
-            pyo3::prepare_freethreaded_python();
-            pyo3::Python::with_gil(|py| {
-                for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                    let mask = RoaringLandmask::new_with_provider(py, provider).unwrap();
-                    assert!(mask.contains(15., 65.6));
-                    assert!(mask.contains(10., 60.0));
-                    b.iter(|| mask.contains(15., 65.6));
-                }
-            })
+        Ok(self.level(x, y))
+    }
+
+    /// Classify point (x, y) using the mask only, as surely in the ocean, surely on land, or in a
+    /// coastal cell where the shapes have to be checked.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// With lakes as water land cells are always coastal, since the lake shores are not part of
+    /// the coastline.
+    pub fn classify(&self, x: f64, y: f64) -> CellClass {
+        assert!(y >= -90. && y <= 90.);
+
+        let x = self.longitude(x);
+
+        match self.mask.classify_unchecked(x, y) {
+            CellClass::Land if self.lakes_as_water => CellClass::Coastal,
+            class => class,
         }
+    }
 
-        #[bench]
-        fn test_contains_in_ocean(b: &mut Bencher) {
-            pyo3::prepare_freethreaded_python();
-            pyo3::Python::with_gil(|py| {
-                for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                    let mask = RoaringLandmask::new_with_provider(py, provider).unwrap();
-                    assert!(!mask.contains(5., 65.6));
-                    b.iter(|| mask.contains(5., 65.6));
-                }
-            });
+    /// Same as `classify`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_classify(&self, x: f64, y: f64) -> Result<CellClass, LandmaskError> {
+        self.check_points([(x, y)])?;
+
+        Ok(self.classify(x, y))
+    }
+
+    /// Check if the segment from (x0, y0) to (x1, y1) touches land.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// The segment goes the shortest way around the Earth, so a segment from 179 to -179 crosses
+    /// the antimeridian. The cells of the landmask along the segment are checked first, and only
+    /// segments passing through a land cell are checked against the shapes.
+    pub fn intersects_segment(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        assert!(y0 >= -90. && y0 <= 90.);
+        assert!(y1 >= -90. && y1 <= 90.);
+
+        let x0 = self.longitude(x0);
+        let x1 = x0 + (self.longitude(x1) - x0 + 180.).rem_euclid(360.) - 180.;
+
+        self.mask.intersects_segment_unchecked(x0, y0, x1, y1)
+            && self.shapes.intersects_segment_unchecked(x0, y0, x1, y1)
+    }
+
+    /// Same as `intersects_segment`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_intersects_segment(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<bool, LandmaskError> {
+        self.check_points([(x0, y0), (x1, y1)])?;
+
+        Ok(self.intersects_segment(x0, y0, x1, y1))
+    }
+
+    /// Find the first point where the segment from (x0, y0) to (x1, y1) meets land.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// The segment goes the shortest way around the Earth. Returns the longitude and latitude of
+    /// the point, and the fraction of the segment travelled before meeting land, or `None` if
+    /// the segment does not touch land. A segment starting on land meets land at fraction 0.
+    pub fn first_land_hit(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Option<(f64, f64, f64)> {
+        if self.contains(x0, y0) {
+            return Some((self.longitude(x0), y0, 0.));
+        }
+
+        if !self.intersects_segment(x0, y0, x1, y1) {
+            return None;
+        }
+
+        let x0 = self.longitude(x0);
+        let x1 = x0 + (self.longitude(x1) - x0 + 180.).rem_euclid(360.) - 180.;
+
+        self.shapes
+            .coastline()
+            .first_crossing(x0, y0, x1, y1)
+            .map(|t| {
+                let x = x0 + t * (x1 - x0);
+                let y = y0 + t * (y1 - y0);
+
+                (modulate_longitude(x), y, t)
+            })
+    }
+
+    /// Same as `first_land_hit`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_first_land_hit(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<Option<(f64, f64, f64)>, LandmaskError> {
+        self.check_points([(x0, y0), (x1, y1)])?;
+
+        Ok(self.first_land_hit(x0, y0, x1, y1))
+    }
+
+    /// Find the closest point in the ocean that is at least `min_offshore` (m) from the coast.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// Points in the ocean far enough from the coast are returned as they are. Other points are
+    /// first moved out from the nearest coastline along its normal. If that does not give a
+    /// point in the ocean, the cells of the landmask are searched outwards (up to about one
+    /// degree) for the closest ocean cell far enough from the coast, and the point is then
+    /// refined against the shapes along the line towards the cell.
+    ///
+    /// Returns `None` if no ocean point is found.
+    pub fn nearest_ocean_point(&self, x: f64, y: f64, min_offshore: f64) -> Option<(f64, f64)> {
+        assert!(y >= -90. && y <= 90.);
+
+        let x = self.longitude(x);
+
+        let offshore = |x: f64, y: f64| {
+            let x = modulate_longitude(x);
+
+            y > -90.
+                && y < 90.
+                && !self.contains_unchecked(x, y)
+                && self
+                    .nearest_coast_unchecked(x, y)
+                    .map_or(f64::INFINITY, |n| n.distance)
+                    >= min_offshore
+        };
+
+        if offshore(x, y) {
+            return Some((x, y));
+        }
+
+        // Metres per degree latitude.
+        let k = coast::EARTH_RADIUS * std::f64::consts::PI / 180.;
+
+        // Move out from the nearest coastline.
+        let n = self.nearest_coast_unchecked(x, y)?;
+        let d = min_offshore * 1.01 + 10.;
+        let (px, py) = (
+            n.x + d * n.normal_x / (k * n.y.to_radians().cos()),
+            n.y + d * n.normal_y / k,
+        );
+
+        if offshore(px, py) {
+            return Some((modulate_longitude(px), py));
+        }
+
+        // Search the landmask outwards for the closest ocean cell.
+        let (cx, cy) = self.mask.cell(x, y);
+        let c = y.to_radians().cos().max(0.01);
+        let mut best: Option<(f64, f64, f64)> = None;
+
+        for r in 1..=OCEAN_SEARCH_CELLS {
+            if let Some((bd, _, _)) = best {
+                if (r - 1) as f64 * self.mask.dy() * k * c > bd {
+                    break;
+                }
+            }
+
+            for dy in -r..=r {
+                let step = if dy.abs() == r { 1 } else { 2 * r as usize };
+
+                for dx in (-r..=r).step_by(step) {
+                    if self.mask.contains_cell(cx + dx, cy + dy) {
+                        continue;
+                    }
+
+                    let (px, py) = self.mask.cell_centre(cx + dx, cy + dy);
+                    let d = coast::haversine(x, y, px, py);
+
+                    if best.map_or(true, |(bd, _, _)| d < bd) && offshore(px, py) {
+                        best = Some((d, px, py));
+                    }
+                }
+            }
+        }
+
+        let (_, px, py) = best?;
+
+        // Refine towards the point, from the last point in the ocean.
+        let (mut lo, mut hi) = (0., 1.);
+        for _ in 0..16 {
+            let t = (lo + hi) / 2.;
+            if offshore(x + t * (px - x), y + t * (py - y)) {
+                hi = t;
+            } else {
+                lo = t;
+            }
+        }
+
+        Some((modulate_longitude(x + hi * (px - x)), y + hi * (py - y)))
+    }
+
+    /// Same as `nearest_ocean_point`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_nearest_ocean_point(
+        &self,
+        x: f64,
+        y: f64,
+        min_offshore: f64,
+    ) -> Result<Option<(f64, f64)>, LandmaskError> {
+        self.check_points([(x, y)])?;
+
+        Ok(self.nearest_ocean_point(x, y, min_offshore))
+    }
+
+    /// Distance (m) from point (x, y) to the nearest coastline.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// The distance is the great-circle distance, and is positive both on land and in the
+    /// ocean.
+    pub fn distance_to_coast(&self, x: f64, y: f64) -> f64 {
+        self.nearest_coast(x, y)
+            .map_or(f64::INFINITY, |n| n.distance)
+    }
+
+    /// Same as `distance_to_coast`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_distance_to_coast(&self, x: f64, y: f64) -> Result<f64, LandmaskError> {
+        self.check_points([(x, y)])?;
+
+        Ok(self.distance_to_coast(x, y))
+    }
+
+    /// Find the point on the coastline closest to point (x, y), along with the bearing to it
+    /// and the normal of the coastline pointing out of land. See `Nearest`.
+    pub fn nearest_coast(&self, x: f64, y: f64) -> Option<Nearest> {
+        assert!(y >= -90. && y <= 90.);
+
+        self.nearest_coast_unchecked(self.longitude(x), y)
+    }
+
+    /// Same as `nearest_coast`, but does not check for bounds. `x` must be in [-180, 180).
+    fn nearest_coast_unchecked(&self, x: f64, y: f64) -> Option<Nearest> {
+        self.shapes.coastline().nearest(x, y)
+    }
+
+    /// Same as `nearest_coast`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_nearest_coast(&self, x: f64, y: f64) -> Result<Option<Nearest>, LandmaskError> {
+        self.check_points([(x, y)])?;
+
+        Ok(self.nearest_coast(x, y))
+    }
+
+    /// Rasterize the landmask onto a regular grid of `nx` by `ny` cells, from `lon_min` to
+    /// `lon_max` and `lat_min` to `lat_max`. Grids with `lon_max` less than or equal to `lon_min`
+    /// cross the antimeridian, with equal longitudes the grid goes around the whole Earth.
+    ///
+    /// Returns an array with shape `(ny, nx)`, starting in the south-west corner. The value of
+    /// each cell is the fraction of land for `RasterRule::Fraction`, and otherwise 1 for land
+    /// and 0 for ocean according to the `rule`. The exact fraction of land is found by
+    /// intersecting the cells with the coastline, cells that are all ocean in the landmask are
+    /// skipped.
+    ///
+    /// `lon_min` and `lon_max` must be in the longitude convention, the cells in between may
+    /// cross the antimeridian.
+    #[allow(clippy::too_many_arguments)]
+    pub fn rasterize(
+        &self,
+        lon_min: f64,
+        lon_max: f64,
+        nx: usize,
+        lat_min: f64,
+        lat_max: f64,
+        ny: usize,
+        rule: RasterRule,
+    ) -> Array2<f64> {
+        assert!(lat_min >= -90. && lat_max <= 90. && lat_min < lat_max);
+
+        // Panics if the bounds are outside the longitude convention.
+        self.longitude(lon_min);
+        self.longitude(lon_max);
+
+        let lon_max = if lon_max <= lon_min {
+            lon_max + 360.
+        } else {
+            lon_max
+        };
+
+        let dx = (lon_max - lon_min) / nx as f64;
+        let dy = (lat_max - lat_min) / ny as f64;
+
+        let land = |b: bool| if b { 1. } else { 0. };
+
+        let mut raster = Array2::zeros((ny, nx));
+
+        use ndarray::Zip;
+        Zip::indexed(&mut raster).par_for_each(|(j, i), v| {
+            let x0 = lon_min + i as f64 * dx;
+            let y0 = lat_min + j as f64 * dy;
+            let (x1, y1) = (x0 + dx, y0 + dy);
+
+            if rule == RasterRule::Centre {
+                let x = modulate_longitude((x0 + x1) / 2.);
+                *v = land(self.contains_unchecked(x, (y0 + y1) / 2.));
+                return;
+            }
+
+            let f = if self.mask.all_ocean(x0, y0, x1, y1) {
+                0.
+            } else {
+                self.polygon_land_fraction(&[[x0, y0], [x1, y0], [x1, y1], [x0, y1]])
+            };
+
+            *v = match rule {
+                RasterRule::AnyLand => land(f > 0.),
+                RasterRule::AllLand => land(f >= 1. - 1e-9),
+                RasterRule::Majority => land(f > 0.5),
+                _ => f,
+            };
+        });
+
+        raster
+    }
+
+    /// Same as `rasterize`, but returns an error for invalid bounds instead of panicking, see
+    /// `try_contains`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn try_rasterize(
+        &self,
+        lon_min: f64,
+        lon_max: f64,
+        nx: usize,
+        lat_min: f64,
+        lat_max: f64,
+        ny: usize,
+        rule: RasterRule,
+    ) -> Result<Array2<f64>, LandmaskError> {
+        self.check_points([(lon_min, lat_min), (lon_max, lat_max)])?;
+
+        if lat_min >= lat_max {
+            return Err(LandmaskError::InvalidInput(format!(
+                "lat_min ({}) must be less than lat_max ({})",
+                lat_min, lat_max
+            )));
+        }
+
+        Ok(self.rasterize(lon_min, lon_max, nx, lat_min, lat_max, ny, rule))
+    }
+
+    /// Fraction of land in each cell of a curvilinear grid, e.g. of an ocean model.
+    ///
+    /// `lon` and `lat` are the corners of the cells, with shape `(ny + 1, nx + 1)`. Returns the
+    /// fraction of land in each of the quadrilateral cells, with shape `(ny, nx)`. The cells are
+    /// intersected with the coastline, cells that are all ocean in the landmask or do not touch
+    /// the coastline are not intersected. The longitudes of the corners must be in the longitude
+    /// convention.
+    pub fn curvilinear_land_fraction(
+        &self,
+        lon: ArrayView2<f64>,
+        lat: ArrayView2<f64>,
+    ) -> Array2<f64> {
+        assert_eq!(lon.shape(), lat.shape());
+        assert!(lon.nrows() > 1 && lon.ncols() > 1);
+
+        let (ny, nx) = (lon.nrows() - 1, lon.ncols() - 1);
+
+        let mut fraction = Array2::zeros((ny, nx));
+
+        use ndarray::Zip;
+        Zip::indexed(&mut fraction).par_for_each(|(j, i), f| {
+            let x0 = self.longitude(lon[(j, i)]);
+            let corner = |j: usize, i: usize| {
+                // Same side of the antimeridian as the first corner.
+                let x = x0 + (self.longitude(lon[(j, i)]) - x0 + 180.).rem_euclid(360.) - 180.;
+                [x, lat[(j, i)]]
+            };
+
+            let quad = [
+                corner(j, i),
+                corner(j, i + 1),
+                corner(j + 1, i + 1),
+                corner(j + 1, i),
+            ];
+
+            *f = self.quad_land_fraction(quad);
+        });
+
+        fraction
+    }
+
+    /// Same as `curvilinear_land_fraction`, but returns an error for mismatched or too small
+    /// grids and invalid corners instead of panicking.
+    pub fn try_curvilinear_land_fraction(
+        &self,
+        lon: ArrayView2<f64>,
+        lat: ArrayView2<f64>,
+    ) -> Result<Array2<f64>, LandmaskError> {
+        if lon.shape() != lat.shape() {
+            return Err(LandmaskError::InvalidInput(format!(
+                "lon and lat have different shapes: {:?} and {:?}",
+                lon.shape(),
+                lat.shape()
+            )));
+        }
+
+        if lon.nrows() < 2 || lon.ncols() < 2 {
+            return Err(LandmaskError::InvalidInput(
+                "the grid needs at least two corners in each direction".into(),
+            ));
+        }
+
+        self.check_points(lon.iter().copied().zip(lat.iter().copied()))?;
+
+        Ok(self.curvilinear_land_fraction(lon, lat))
+    }
+
+    /// Fraction of the quadrilateral that is land. The quadrilateral is split into two
+    /// triangles, which works for both orientations and non-convex quadrilaterals.
+    fn quad_land_fraction(&self, q: [[f64; 2]; 4]) -> f64 {
+        let (x0, x1) = q
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), p| {
+                (a.min(p[0]), b.max(p[0]))
+            });
+        let (y0, y1) = q
+            .iter()
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(a, b), p| {
+                (a.min(p[1]), b.max(p[1]))
+            });
+
+        assert!(y0 >= -90. && y1 <= 90.);
+
+        if self.mask.all_ocean(x0, y0, x1, y1) {
+            return 0.;
+        }
+
+        let signed = |a: [f64; 2], b: [f64; 2], c: [f64; 2]| {
+            ((b[0] - a[0]) * (c[1] - a[1]) - (c[0] - a[0]) * (b[1] - a[1])) / 2.
+        };
+
+        // Split along the diagonal inside the quadrilateral.
+        let tris = if signed(q[0], q[1], q[2]) * signed(q[0], q[2], q[3]) > 0. {
+            [[q[0], q[1], q[2]], [q[0], q[2], q[3]]]
+        } else {
+            [[q[1], q[2], q[3]], [q[1], q[3], q[0]]]
+        };
+
+        let mut land = 0.;
+        let mut area = 0.;
+
+        for mut t in tris {
+            let a = signed(t[0], t[1], t[2]);
+            if a < 0. {
+                t.swap(1, 2);
+            }
+
+            land += self.polygon_land_fraction(&t) * a.abs();
+            area += a.abs();
+        }
+
+        if area > 0. {
+            (land / area).clamp(0., 1.)
+        } else {
+            0.
+        }
+    }
+
+    /// Fraction of the convex, counter-clockwise, polygon that is land.
+    fn polygon_land_fraction(&self, poly: &[[f64; 2]]) -> f64 {
+        let n = poly.len();
+        let area = (0..n)
+            .map(|i| {
+                let (p, q) = (poly[i], poly[(i + 1) % n]);
+                (p[0] * q[1] - q[0] * p[1]) / 2.
+            })
+            .sum::<f64>();
+
+        if area <= 0. {
+            return 0.;
+        }
+
+        let land = self.shapes.coastline().land_area(poly, |x, y| {
+            self.contains_unchecked(modulate_longitude(x), y)
+        });
+
+        (land / area).clamp(0., 1.)
+    }
+}
+
+#[cfg(feature = "python")]
+/// Collect the segments that hit land into arrays of index, longitude, latitude and fraction.
+#[allow(clippy::type_complexity)]
+fn hits_to_arrays<I>(
+    py: Python,
+    hits: I,
+) -> (
+    Py<PyArray<usize, numpy::Ix1>>,
+    Py<PyArray<f64, numpy::Ix1>>,
+    Py<PyArray<f64, numpy::Ix1>>,
+    Py<PyArray<f64, numpy::Ix1>>,
+)
+where
+    I: Iterator<Item = Option<(f64, f64, f64)>>,
+{
+    let mut idx = Vec::new();
+    let mut x = Vec::new();
+    let mut y = Vec::new();
+    let mut t = Vec::new();
+
+    for (i, hit) in hits.enumerate() {
+        if let Some((hx, hy, ht)) = hit {
+            idx.push(i);
+            x.push(hx);
+            y.push(hy);
+            t.push(ht);
+        }
+    }
+
+    (
+        PyArray::from_vec(py, idx).to_owned(),
+        PyArray::from_vec(py, x).to_owned(),
+        PyArray::from_vec(py, y).to_owned(),
+        PyArray::from_vec(py, t).to_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_ms() {
+        let _ms = RoaringLandmask::new_with_provider(LandmaskProvider::Gshhg).unwrap();
+        let _ms = RoaringLandmask::new_with_provider(LandmaskProvider::Osm).unwrap();
+    }
+
+    #[test]
+    fn test_np() {
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Gshhg).unwrap();
+        assert!(!mask.contains(5., 90.));
+
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Osm).unwrap();
+        assert!(!mask.contains(5., 90.));
+    }
+
+    #[test]
+    fn test_sp() {
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Gshhg).unwrap();
+        assert!(mask.contains(5., -89.99));
+
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Osm).unwrap();
+        assert!(mask.contains(5., -89.99));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_sp_oob() {
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Gshhg).unwrap();
+        assert!(mask.contains(5., -90.));
+
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Osm).unwrap();
+        assert!(mask.contains(5., -90.));
+    }
+
+    #[test]
+    fn test_dateline_wrap() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+            // Close to NP
+            assert!(!mask.contains(5., 89.));
+            // Close to SP
+            assert!(mask.contains(5., -89.));
+            // Within bounds
+            let x = (-180..180).map(f64::from).collect::<Vec<_>>();
+            let m = x.iter().map(|x| mask.contains(*x, 65.)).collect::<Vec<_>>();
+            // Wrapped bounds
+            let x = (180..540).map(f64::from).collect::<Vec<_>>();
+            let mm = x.iter().map(|x| mask.contains(*x, 65.)).collect::<Vec<_>>();
+            assert_eq!(m, mm);
+            // Wrapped westwards
+            let x = (-540..-180).map(f64::from).collect::<Vec<_>>();
+            let mm = x.iter().map(|x| mask.contains(*x, 65.)).collect::<Vec<_>>();
+            assert_eq!(m, mm);
+        }
+    }
+
+    #[test]
+    fn test_longitude_convention() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider)
+                .unwrap()
+                .with_longitude_convention(LongitudeConvention::Positive);
+
+            // Norway east of 0, and Alaska west of 180.
+            assert!(mask.try_contains(15., 65.6).unwrap());
+            assert!(mask.try_contains(360. - 150., 65.).unwrap());
+            assert!(!mask.try_contains(360. - 170., 50.).unwrap());
+
+            assert!(matches!(
+                mask.try_contains(-150., 65.),
+                Err(LandmaskError::LongitudeOutOfRange(_))
+            ));
+
+            // The segment and coast queries follow the convention too.
+            assert!(mask.try_intersects_segment(355., 60., 11.5, 57.).unwrap());
+            assert!(mask.try_intersects_segment(-5., 60., 11.5, 57.).is_err());
+            assert!(mask.try_distance_to_coast(350., 60.).unwrap() > 0.);
+            assert!(mask.try_nearest_coast(-10., 60.).is_err());
+            assert!(mask.try_classify(-150., 65.).is_err());
+
+            let mask = mask.with_longitude_convention(LongitudeConvention::Signed);
+            assert!(mask.try_contains(-150., 65.).unwrap());
+            assert!(mask.try_contains(210., 65.).is_err());
+            assert!(mask.try_first_land_hit(4., 60., 190., 57.).is_err());
+            assert!(mask.try_nearest_ocean_point(200., 60., 0.).is_err());
+        }
+    }
+
+    #[test]
+    fn test_intersects_segment() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // Both ends in the ocean, across southern Norway.
+            assert!(!mask.contains(4., 60.) && !mask.contains(11.5, 57.));
+            assert!(mask.intersects_segment(4., 60., 11.5, 57.));
+
+            // Ends on land, and in the ocean.
+            assert!(mask.intersects_segment(5., 65.6, 15., 65.6));
+            assert!(!mask.intersects_segment(0., 66., 2., 68.));
+
+            // Across the antimeridian, through Fiji.
+            assert!(mask.intersects_segment(178., -16.5, -179.5, -16.5));
+            assert!(!mask.intersects_segment(179.5, 0., -179.5, 0.));
+        }
+    }
+
+    #[test]
+    fn test_first_land_hit() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // From the ocean onto land.
+            let (x, y, t) = mask.first_land_hit(5., 65.6, 15., 65.6).unwrap();
+            assert!(t > 0. && t < 1.);
+            assert_eq!(y, 65.6);
+            assert!(x > 5. && x < 15.);
+            assert!(mask.distance_to_coast(x, y) < 1.);
+
+            // Starting on land, and not hitting land.
+            assert_eq!(
+                mask.first_land_hit(15., 65.6, 5., 65.6),
+                Some((15., 65.6, 0.))
+            );
+            assert_eq!(mask.first_land_hit(0., 66., 2., 68.), None);
+        }
+    }
+
+    #[test]
+    fn test_nearest_ocean_point() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // Already in the ocean.
+            assert_eq!(mask.nearest_ocean_point(5., 65.6, 1000.), Some((5., 65.6)));
+
+            // Just inland from Stad, western Norway.
+            let (x, y) = mask.nearest_ocean_point(5.25, 62.15, 1000.).unwrap();
+            assert!(!mask.contains(x, y));
+            assert!(mask.distance_to_coast(x, y) >= 1000.);
+            assert!(coast::haversine(5.25, 62.15, x, y) < 20_000.);
+
+            // Deep inland.
+            assert_eq!(mask.nearest_ocean_point(25., 0., 1000.), None);
+        }
+    }
+
+    #[test]
+    fn test_rasterize() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // Coast of western Norway.
+            let r = |rule| mask.rasterize(4., 6., 8, 60., 62., 8, rule);
+
+            let centre = r(RasterRule::Centre);
+            let any = r(RasterRule::AnyLand);
+            let all = r(RasterRule::AllLand);
+            let majority = r(RasterRule::Majority);
+            let fraction = r(RasterRule::Fraction);
+
+            assert_eq!(fraction.shape(), &[8, 8]);
+            assert!(fraction.iter().all(|f| (0. ..=1.).contains(f)));
+            assert!(fraction.iter().any(|f| *f > 0. && *f < 1.));
+
+            for (((a, l), m), f) in any.iter().zip(&all).zip(&majority).zip(&fraction) {
+                assert!(a >= m && m >= l);
+                assert_eq!(*a == 1., *f > 0.);
+                assert_eq!(*m == 1., *f > 0.5);
+            }
+
+            for (c, a) in centre.iter().zip(&any) {
+                assert!(a >= c);
+            }
+
+            // Open ocean and across the antimeridian.
+            let f = mask.rasterize(0., 2., 4, 66., 68., 4, RasterRule::Fraction);
+            assert!(f.iter().all(|f| *f == 0.));
+
+            let fiji = mask.rasterize(179., -179., 4, -17., -16., 2, RasterRule::AnyLand);
+            assert!(fiji.iter().any(|f| *f == 1.));
+
+            // Iceland, west of 0.
+            let iceland = mask.rasterize(-20., -16., 4, 64., 66., 4, RasterRule::Fraction);
+            assert!(iceland.iter().any(|f| *f > 0.));
+
+            let mask = mask.with_longitude_convention(LongitudeConvention::Signed);
+            let r = mask.rasterize(179., -179., 4, -17., -16., 2, RasterRule::AnyLand);
+            assert_eq!(r, fiji);
+            let r = mask.rasterize(-20., -16., 4, 64., 66., 4, RasterRule::Fraction);
+            assert_eq!(r, iceland);
+            assert!(mask
+                .try_rasterize(179., 181., 4, -17., -16., 2, RasterRule::AnyLand)
+                .is_err());
+
+            let mask = mask.with_longitude_convention(LongitudeConvention::Positive);
+            let r = mask.rasterize(179., 181., 4, -17., -16., 2, RasterRule::AnyLand);
+            assert_eq!(r, fiji);
+            let r = mask.rasterize(340., 344., 4, 64., 66., 4, RasterRule::Fraction);
+            for (r, f) in r.iter().zip(&iceland) {
+                assert!((r - f).abs() < 1e-6);
+            }
+            assert!(mask
+                .try_rasterize(-20., -16., 4, 64., 66., 4, RasterRule::Fraction)
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_curvilinear_land_fraction() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // A regular grid should match the raster.
+            let lon = Array2::from_shape_fn((9, 9), |(_, i)| 4. + i as f64 * 0.25);
+            let lat = Array2::from_shape_fn((9, 9), |(j, _)| 60. + j as f64 * 0.25);
+
+            let f = mask.curvilinear_land_fraction(lon.view(), lat.view());
+            let r = mask.rasterize(4., 6., 8, 60., 62., 8, RasterRule::Fraction);
+
+            for (f, r) in f.iter().zip(&r) {
+                assert!((f - r).abs() < 1e-6);
+            }
+
+            // Rotated and flipped grid.
+            let lon = Array2::from_shape_fn((9, 9), |(j, i)| {
+                4. + (8 - i) as f64 * 0.25 + j as f64 * 0.05
+            });
+            let lat =
+                Array2::from_shape_fn((9, 9), |(j, i)| 60. + j as f64 * 0.25 + i as f64 * 0.05);
+
+            let f = mask.curvilinear_land_fraction(lon.view(), lat.view());
+            assert!(f.iter().all(|f| (0. ..=1.).contains(f)));
+            assert!(f.iter().any(|f| *f > 0. && *f < 1.));
+
+            // Across the antimeridian
+            let lon = Array2::from_shape_fn((3, 3), |(_, i)| {
+                179.5 + i as f64 * 0.5 - 360. * (i / 2) as f64
+            });
+            let lat = Array2::from_shape_fn((3, 3), |(j, _)| -17. + j as f64 * 0.5);
+
+            let f = mask.curvilinear_land_fraction(lon.view(), lat.view());
+            let r = mask.rasterize(179.5, -179.5, 2, -17., -16., 2, RasterRule::Fraction);
+
+            for (f, r) in f.iter().zip(&r) {
+                assert!((f - r).abs() < 1e-6);
+            }
+
+            // With the longitude conventions, across the antimeridian and west of 0.
+            let iceland_lon = Array2::from_shape_fn((5, 5), |(_, i)| -20. + i as f64);
+            let iceland_lat = Array2::from_shape_fn((5, 5), |(j, _)| 64. + j as f64 * 0.5);
+            let iceland = mask.curvilinear_land_fraction(iceland_lon.view(), iceland_lat.view());
+            assert!(iceland.iter().any(|f| *f > 0.));
+
+            let mask = mask.with_longitude_convention(LongitudeConvention::Signed);
+            let s = mask.curvilinear_land_fraction(lon.view(), lat.view());
+            assert_eq!(s, f);
+            let s = mask.curvilinear_land_fraction(iceland_lon.view(), iceland_lat.view());
+            assert_eq!(s, iceland);
+            let positive = lon.mapv(|x| x.rem_euclid(360.));
+            assert!(mask
+                .try_curvilinear_land_fraction(positive.view(), lat.view())
+                .is_err());
+
+            let mask = mask.with_longitude_convention(LongitudeConvention::Positive);
+            let p = mask.curvilinear_land_fraction(positive.view(), lat.view());
+            assert_eq!(p, f);
+            let iceland_positive = iceland_lon.mapv(|x| x.rem_euclid(360.));
+            let p = mask.curvilinear_land_fraction(iceland_positive.view(), iceland_lat.view());
+            assert_eq!(p, iceland);
+            assert!(mask
+                .try_curvilinear_land_fraction(lon.view(), lat.view())
+                .is_err());
+        }
+    }
+
+    #[test]
+    fn test_distance_to_coast() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // Middle of the Norwegian sea, and inland Norway.
+            let d = mask.distance_to_coast(0., 66.);
+            assert!(d > 100_000. && d < 700_000.);
+            assert!(mask.distance_to_coast(10., 60.) > 0.);
+
+            // Wrapped longitude
+            assert_eq!(
+                mask.distance_to_coast(0., 66.),
+                mask.distance_to_coast(360., 66.)
+            );
+        }
+    }
+
+    #[test]
+    fn test_nearest_coast() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // West of Norway, the coast is to the east and the normal points west.
+            let n = mask.nearest_coast(3., 61.).unwrap();
+            assert!(n.x > 4. && n.x < 6.);
+            assert!(n.bearing > 0. && n.bearing < 180.);
+            assert!((n.normal_x.hypot(n.normal_y) - 1.).abs() < 1e-9);
+
+            // Across the antimeridian, Fiji.
+            let n = mask.nearest_coast(179.9, -16.5).unwrap();
+            let m = mask.nearest_coast(-180.1, -16.5).unwrap();
+            assert!((n.x - m.x).abs() < 1e-9 && (n.y - m.y).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            // Without the interior cells every land cell is coastal.
+            assert_eq!(mask.classify(15., 65.6), CellClass::Coastal);
+
+            let mask = mask.with_interior_cells();
+            assert_eq!(mask.classify(15., 65.6), CellClass::Land);
+            assert_eq!(mask.classify(5., 65.6), CellClass::Ocean);
+
+            // Points classified as land or ocean agree with the shapes.
+            for i in 0..1000 {
+                let x = 4. + 8. * (i % 40) as f64 / 40.;
+                let y = 58. + 4. * (i / 40) as f64 / 25.;
+
+                match mask.classify(x, y) {
+                    CellClass::Land => assert!(mask.contains(x, y)),
+                    CellClass::Ocean => assert!(!mask.contains(x, y)),
+                    CellClass::Coastal => {}
+                }
+            }
+        }
+    }
+
+    fn rect(x0: f64, y0: f64, x1: f64, y1: f64) -> Shapes {
+        let ring = vec![[x0, y0], [x1, y0], [x1, y1], [x0, y1], [x0, y0]];
+        Shapes::from_wkb(&wkb::write_rings(&[(ring, true)])).unwrap()
+    }
+
+    #[test]
+    fn test_level() {
+        let levels = Levels::from_shapes(vec![
+            rect(10., 60., 20., 70.),
+            rect(14.5, 65., 16., 66.2),
+            rect(15.3, 65.8, 15.6, 66.),
+        ]);
+
+        let mask = RoaringLandmask::new().unwrap();
+        assert_eq!(mask.level(15., 65.6), Level::Land);
+        assert_eq!(mask.level(5., 65.6), Level::Ocean);
+
+        // Lakes are land, but their level is still known.
+        let mask = RoaringLandmask::new_with_levels(LandmaskProvider::Gshhg, levels.clone(), false)
+            .unwrap()
+            .with_interior_cells();
+        assert!(!mask.lakes_as_water());
+        assert_eq!(mask.level(15., 65.6), Level::Lake);
+        assert_eq!(mask.level(15.45, 65.9), Level::IslandInLake);
+        assert_eq!(mask.level(12., 65.6), Level::Land);
+        assert_eq!(mask.level(5., 65.6), Level::Ocean);
+        assert!(mask.contains(15., 65.6));
+        assert_eq!(mask.classify(15., 65.6), CellClass::Land);
+    }
+
+    #[test]
+    fn test_new_with_levels() {
+        let levels = Levels::from_shapes(vec![
+            rect(10., 60., 20., 70.),
+            rect(14.5, 65., 16., 66.2),
+            rect(15.3, 65.8, 15.6, 66.),
+        ]);
+
+        let mask = RoaringLandmask::new_with_levels(LandmaskProvider::Gshhg, levels, true).unwrap();
+        assert!(mask.lakes_as_water());
+        assert_eq!(mask.level(15., 65.6), Level::Lake);
+        assert!(!mask.contains(15., 65.6));
+        assert!(mask.contains(15.45, 65.9));
+        assert!(mask.contains(12., 65.6));
+        assert!(!mask.contains(5., 65.6));
+        assert_eq!(mask.classify(15., 65.6), CellClass::Coastal);
+    }
+
+    #[test]
+    fn test_try_contains() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+            assert!(mask.try_contains(15., 65.6).unwrap());
+            assert!(!mask.try_contains(5., 65.6).unwrap());
+
+            assert!(matches!(
+                mask.try_contains(5., 95.),
+                Err(LandmaskError::LatitudeOutOfRange(_))
+            ));
+            assert!(matches!(
+                mask.try_contains(f64::NAN, 60.),
+                Err(LandmaskError::NonFinite { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_try_queries() {
+        let mask = RoaringLandmask::new().unwrap();
+
+        assert!(mask.try_intersects_segment(4., 60., 11.5, 57.).unwrap());
+        assert!(mask
+            .try_intersects_segment(4., 60., 11.5, f64::NAN)
+            .is_err());
+        assert!(mask.try_first_land_hit(4., 95., 11.5, 57.).is_err());
+        assert!(mask
+            .try_nearest_ocean_point(f64::INFINITY, 60., 0.)
+            .is_err());
+        assert!(mask.try_distance_to_coast(5., -95.).is_err());
+        assert!(mask.try_level(5., 95.).is_err());
+
+        assert!(mask
+            .try_rasterize(4., 6., 8, 62., 60., 8, RasterRule::Centre)
+            .is_err());
+
+        let lon = ndarray::arr2(&[[4., 5.], [4., 5.]]);
+        let lat = ndarray::arr2(&[[60., 60.], [61., 61.]]);
+        assert!(mask
+            .try_curvilinear_land_fraction(lon.view(), lat.view())
+            .is_ok());
+        assert!(mask
+            .try_curvilinear_land_fraction(lon.view(), lat.slice(ndarray::s![..1, ..]))
+            .is_err());
+    }
+
+    #[test]
+    fn test_contains_invalid() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+
+            let x = ndarray::arr1(&[15., 5., f64::NAN, 5.]);
+            let y = ndarray::arr1(&[65.6, 65.6, 60., -95.]);
+            let mut out = ndarray::Array1::from_elem(4, false);
+
+            assert!(mask
+                .contains_array_with(x.view(), y.view(), out.view_mut(), InvalidPolicy::Raise)
+                .is_err());
+
+            mask.contains_array_with(x.view(), y.view(), out.view_mut(), InvalidPolicy::Ocean)
+                .unwrap();
+            assert_eq!(out, ndarray::arr1(&[true, false, false, false]));
+
+            mask.contains_array_with_par(x.view(), y.view(), out.view_mut(), InvalidPolicy::Land)
+                .unwrap();
+            assert_eq!(out, ndarray::arr1(&[true, false, true, true]));
+
+            let mut out = ndarray::Array1::from_elem(4, 0);
+            mask.contains_array_i8(x.view(), y.view(), out.view_mut());
+            assert_eq!(out, ndarray::arr1(&[1, 0, -1, -1]));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_not_on_earth_north() {
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Gshhg).unwrap();
+        assert!(!mask.contains(5., 95.));
+
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Osm).unwrap();
+        assert!(!mask.contains(5., 95.));
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_not_on_earth_south() {
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Gshhg).unwrap();
+        assert!(!mask.contains(5., -95.));
+
+        let mask = RoaringLandmask::new_with_provider(LandmaskProvider::Osm).unwrap();
+        assert!(!mask.contains(5., -95.));
+    }
+
+    #[cfg(feature = "nightly")]
+    mod benches {
+        use super::*;
+        use test::Bencher;
+
+        #[bench]
+        fn test_contains_on_land(b: &mut Bencher) {
+            for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+                let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+                assert!(mask.contains(15., 65.6));
+                assert!(mask.contains(10., 60.0));
+                b.iter(|| mask.contains(15., 65.6));
+            }
+        }
+
+        #[bench]
+        fn test_contains_in_ocean(b: &mut Bencher) {
+            for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+                let mask = RoaringLandmask::new_with_provider(provider).unwrap();
+                assert!(!mask.contains(5., 65.6));
+                b.iter(|| mask.contains(5., 65.6));
+            }
         }
 
+        #[cfg(feature = "python")]
         #[bench]
         fn test_contains_many(b: &mut Bencher) {
             pyo3::prepare_freethreaded_python();
             pyo3::Python::with_gil(|py| {
                 for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                    let mask = RoaringLandmask::new_with_provider(py, provider).unwrap();
+                    let mask = RoaringLandmask::new_with_provider(provider).unwrap();
                     let (x, y): (Vec<f64>, Vec<f64>) = (0..360 * 2)
                         .map(|v| v as f64 * 0.5 - 180.)
                         .map(|x| {
@@ -340,20 +1933,30 @@ mod tests {
                         let x = x.to_dyn().readonly();
                         let y = y.to_dyn().readonly();
 
-                        let onland = mask.contains_many(py, x, y);
-                        assert!(onland.as_ref(py).len() == len);
+                        let onland = mask
+                            .contains_many(
+                                py,
+                                PyCoords::F64(x),
+                                PyCoords::F64(y),
+                                InvalidPolicy::Raise,
+                                1.,
+                                None,
+                            )
+                            .unwrap();
+                        assert!(onland.as_ref(py).len().unwrap() == len);
                     });
                 }
             })
         }
 
+        #[cfg(feature = "python")]
         #[bench]
         #[ignore]
         fn test_contains_many_par(b: &mut Bencher) {
             pyo3::prepare_freethreaded_python();
             pyo3::Python::with_gil(|py| {
                 for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                    let mask = RoaringLandmask::new_with_provider(py, provider).unwrap();
+                    let mask = RoaringLandmask::new_with_provider(provider).unwrap();
                     let (x, y): (Vec<f64>, Vec<f64>) = (0..360 * 2)
                         .map(|v| v as f64 * 0.5 - 180.)
                         .map(|x| {
@@ -381,8 +1984,17 @@ mod tests {
                         let x = x.to_dyn().readonly();
                         let y = y.to_dyn().readonly();
 
-                        let onland = mask.contains_many_par(py, x, y);
-                        assert!(onland.as_ref(py).len() == len);
+                        let onland = mask
+                            .contains_many_par(
+                                py,
+                                PyCoords::F64(x),
+                                PyCoords::F64(y),
+                                InvalidPolicy::Raise,
+                                1.,
+                                None,
+                            )
+                            .unwrap();
+                        assert!(onland.as_ref(py).len().unwrap() == len);
                     });
                 }
             })
@@ -0,0 +1,104 @@
+//! Wrapping longitudes onto the [-180, 180) range of the landmask.
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+use crate::error::LandmaskError;
+
+/// The range of the longitudes given to the queries.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LongitudeConvention {
+    /// Any finite longitude, wrapped around the Earth as many times as needed.
+    #[default]
+    Any,
+    /// Longitudes in [-180, 180].
+    Signed,
+    /// Longitudes in [0, 360].
+    Positive,
+}
+
+impl LongitudeConvention {
+    /// Check that the longitude `x` is in the range of the convention, and wrap it onto
+    /// [-180, 180).
+    pub fn normalize(self, x: f64) -> Result<f64, LandmaskError> {
+        let valid = match self {
+            LongitudeConvention::Any => x.is_finite(),
+            LongitudeConvention::Signed => (-180. ..=180.).contains(&x),
+            LongitudeConvention::Positive => (0. ..=360.).contains(&x),
+        };
+
+        if valid {
+            Ok(modulate_longitude(x))
+        } else {
+            Err(LandmaskError::LongitudeOutOfRange(x))
+        }
+    }
+}
+
+/// Wrap the longitude onto [-180, 180), both 180 and -180 become -180.
+pub fn modulate_longitude(lon: f64) -> f64 {
+    let lon = (lon + 180.).rem_euclid(360.);
+
+    // The remainder of tiny negative values rounds to 360.
+    if lon < 360. {
+        lon - 180.
+    } else {
+        -180.
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_modulate_longitude() {
+        for (x, expected) in [
+            (0., 0.),
+            (15.5, 15.5),
+            (-15.5, -15.5),
+            (180., -180.),
+            (-180., -180.),
+            (179.5, 179.5),
+            (-179.5, -179.5),
+            (190., -170.),
+            (-190., 170.),
+            (350., -10.),
+            (360., 0.),
+            (-360., 0.),
+            (540., -180.),
+            (-540., -180.),
+            (725., 5.),
+            (-725., -5.),
+            (-1e-15, -1e-15),
+        ] {
+            let x = modulate_longitude(x);
+            assert!((x - expected).abs() < 1e-9, "{} != {}", x, expected);
+            assert!((-180. ..180.).contains(&x));
+        }
+
+        // Just west of -180 the remainder may round to 360.
+        let x = f64::from_bits((-180f64).to_bits() + 1);
+        assert!((-180. ..180.).contains(&modulate_longitude(x)));
+    }
+
+    #[test]
+    fn test_conventions() {
+        use LongitudeConvention::*;
+
+        assert_eq!(Any.normalize(-190.).unwrap(), 170.);
+        assert_eq!(Any.normalize(1000.).unwrap(), -80.);
+        assert!(Any.normalize(f64::NAN).is_err());
+
+        assert_eq!(Signed.normalize(180.).unwrap(), -180.);
+        assert_eq!(Signed.normalize(-20.).unwrap(), -20.);
+        assert!(Signed.normalize(190.).is_err());
+        assert!(Signed.normalize(-180.5).is_err());
+
+        assert_eq!(Positive.normalize(0.).unwrap(), 0.);
+        assert_eq!(Positive.normalize(350.).unwrap(), -10.);
+        assert_eq!(Positive.normalize(360.).unwrap(), 0.);
+        assert!(Positive.normalize(-10.).is_err());
+        assert!(Positive.normalize(360.5).is_err());
+    }
+}
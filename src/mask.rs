@@ -1,48 +1,81 @@
-use numpy::{PyArray, PyReadonlyArrayDyn};
+#[cfg(feature = "python")]
+use numpy::{PyArray, PyArrayDyn, PyReadonlyArrayDyn};
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
 use roaring::RoaringTreemap;
 use std::borrow::Borrow;
+use std::convert::TryInto;
 use std::fs::File;
-use std::io;
+use std::io::{self, Read, Write};
 use std::path::Path;
+use std::sync::OnceLock;
 
+#[cfg(feature = "geos")]
+use geos::Geom;
+
+#[cfg(feature = "python")]
+use crate::batch::PyCoords;
+use crate::coast::Coastline;
+#[cfg(feature = "python")]
+use crate::error::InvalidPolicy;
+use crate::error::{check_coordinates, LandmaskError};
+use crate::longitude::LongitudeConvention;
 pub use crate::providers::LandmaskProvider;
+use crate::raster::RasterRule;
+#[cfg(feature = "geos")]
+use crate::shapes::collect_rings;
 
+/// Number of rows of cells in the built-in masks.
 pub const NY: u64 = 43200;
+/// Number of columns of cells in the built-in masks.
 pub const NX: u64 = 86400;
 
-lazy_static! {
-    static ref TRANSFORM: Affine = Affine::make();
-}
-
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Clone, Debug, Default)]
 pub struct RoaringMask {
     tmap: RoaringTreemap,
+    grid: Grid,
+    metadata: MaskMetadata,
+    /// The range of the longitudes given to the point queries.
+    longitudes: LongitudeConvention,
+    /// Coastal and interior cells, see `classify`.
+    cells: OnceLock<Cells>,
 }
 
-#[pyclass]
 #[derive(Clone, Debug, Default)]
+struct Cells {
+    /// Cells the coastline passes through.
+    coastal: RoaringTreemap,
+    /// Land cells that lie entirely inside the land polygons.
+    interior: RoaringTreemap,
+}
+
+/// Classification of a cell in the mask.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CellClass {
+    /// The cell is entirely in the ocean.
+    Ocean,
+    /// The cell is entirely on land.
+    Land,
+    /// The coastline passes through the cell, the point must be checked against the shapes.
+    Coastal,
+}
+
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Clone, Debug, Default, PartialEq)]
 pub struct Affine {
-    #[pyo3(get)]
     sa: f64,
-    #[pyo3(get)]
     sb: f64,
-    #[pyo3(get)]
     sc: f64,
-    #[pyo3(get)]
     sd: f64,
-    #[pyo3(get)]
     se: f64,
-    #[pyo3(get)]
     sf: f64,
 }
 
-#[pymethods]
 impl Affine {
     /// Makes the inverse transform for the landmask image. Goes from latitude, longitude
     /// coordinates to index in mask.
-    #[staticmethod]
     pub fn make() -> Affine {
         // Forward transformation is declared as follows:
         //
@@ -61,6 +94,16 @@ impl Affine {
             sf: 21600.5,
         }
     }
+}
+
+#[cfg_attr(feature = "python", pymethods)]
+impl Affine {
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "make")]
+    fn py_make() -> Affine {
+        Affine::make()
+    }
 
     /// Transform longitude and latitude to index in landmask.
     pub fn apply(&self, x: f64, y: f64) -> (f64, f64) {
@@ -69,49 +112,852 @@ impl Affine {
             x * self.sd + y * self.se + self.sf,
         )
     }
+
+    /// Transform index in landmask to longitude and latitude.
+    pub fn apply_inverse(&self, x: f64, y: f64) -> (f64, f64) {
+        let det = self.sa * self.se - self.sb * self.sd;
+        let (x, y) = (x - self.sc, y - self.sf);
+
+        (
+            (x * self.se - y * self.sb) / det,
+            (y * self.sa - x * self.sd) / det,
+        )
+    }
+}
+
+/// The grid of cells of a mask: the number of columns and rows, and the transform from
+/// longitude and latitude to the index of the cell. The cell in column `x` and row `y` is bit
+/// `y * nx + x` in the mask.
+///
+/// Global grids wrap around the antimeridian, other grids only cover their extent.
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    pub nx: u64,
+    pub ny: u64,
+    pub transform: Affine,
+}
+
+/// The grid of the built-in masks, see `Affine::make`.
+impl Default for Grid {
+    fn default() -> Grid {
+        Grid {
+            nx: NX,
+            ny: NY,
+            transform: Affine::make(),
+        }
+    }
+}
+
+impl Grid {
+    /// A global grid with cells of `resolution` degrees, centred on the meridians and parallels
+    /// at multiples of the resolution like the built-in grid. 360 must be a multiple of the
+    /// resolution.
+    pub fn global(resolution: f64) -> Result<Grid, LandmaskError> {
+        let nx = cells(360., resolution)?;
+        let ny = nx / 2;
+
+        let sa = nx as f64 / 360.;
+        let se = ny as f64 / 180.;
+
+        Ok(Grid {
+            nx,
+            ny,
+            transform: Affine {
+                sa,
+                sb: 0.,
+                sc: 180. * sa + 0.5,
+                sd: 0.,
+                se,
+                sf: 90. * se + 0.5,
+            },
+        })
+    }
+
+    /// A regional grid with cells of `resolution` degrees, with lower left corner (x0, y0) and
+    /// upper right corner (x1, y1). The width and height must be multiples of the resolution.
+    pub fn regional(
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        resolution: f64,
+    ) -> Result<Grid, LandmaskError> {
+        if !(-180. <= x0 && x0 < x1 && x1 <= 180. && -90. <= y0 && y0 < y1 && y1 <= 90.) {
+            return Err(LandmaskError::InvalidInput(format!(
+                "invalid extent: ({}, {}) to ({}, {})",
+                x0, y0, x1, y1
+            )));
+        }
+
+        let nx = cells(x1 - x0, resolution)?;
+        let ny = cells(y1 - y0, resolution)?;
+
+        let sa = nx as f64 / (x1 - x0);
+        let se = ny as f64 / (y1 - y0);
+
+        Ok(Grid {
+            nx,
+            ny,
+            transform: Affine {
+                sa,
+                sb: 0.,
+                sc: -x0 * sa,
+                sd: 0.,
+                se,
+                sf: -y0 * se,
+            },
+        })
+    }
+
+    /// The cell containing point (x, y). The x-index is not wrapped around the antimeridian.
+    pub(crate) fn cell(&self, x: f64, y: f64) -> (i64, i64) {
+        let (x, y) = self.transform.apply(x, y);
+        (x.floor() as i64, y.floor() as i64)
+    }
+
+    /// The bit of cell (x, y), with the x-index wrapped around the antimeridian for global
+    /// grids. Cells outside the grid have none.
+    pub(crate) fn index(&self, x: i64, y: i64) -> Option<u64> {
+        let (nx, ny) = (self.nx as i64, self.ny as i64);
+
+        let x = if self.wraps() { x.rem_euclid(nx) } else { x };
+        if x < 0 || x >= nx || y < 0 || y >= ny {
+            return None;
+        }
+
+        Some(y as u64 * self.nx + x as u64)
+    }
+
+    /// Width of the cells in degrees.
+    pub fn dx(&self) -> f64 {
+        1. / self.transform.sa
+    }
+
+    /// Height of the cells in degrees.
+    pub fn dy(&self) -> f64 {
+        1. / self.transform.se
+    }
+
+    /// The lower left and upper right corners of the grid, as (x0, y0, x1, y1).
+    pub fn extent(&self) -> (f64, f64, f64, f64) {
+        let (x0, y0) = self.transform.apply_inverse(0., 0.);
+        let (x1, y1) = self.transform.apply_inverse(self.nx as f64, self.ny as f64);
+
+        (x0, y0, x1, y1)
+    }
+
+    /// Whether the grid goes around the Earth, and wraps around the antimeridian.
+    pub fn wraps(&self) -> bool {
+        (self.nx as f64 * self.dx() - 360.).abs() < 1e-6
+    }
 }
 
+#[cfg_attr(feature = "python", pymethods)]
+impl Grid {
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "global_")]
+    fn py_global(resolution: f64) -> Result<Grid, LandmaskError> {
+        Grid::global(resolution)
+    }
+
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "regional")]
+    fn py_regional(
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+        resolution: f64,
+    ) -> Result<Grid, LandmaskError> {
+        Grid::regional(x0, y0, x1, y1, resolution)
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "dx")]
+    fn py_dx(&self) -> f64 {
+        self.dx()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "dy")]
+    fn py_dy(&self) -> f64 {
+        self.dy()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "extent")]
+    fn py_extent(&self) -> (f64, f64, f64, f64) {
+        self.extent()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "wraps")]
+    fn py_wraps(&self) -> bool {
+        self.wraps()
+    }
+}
+
+/// The number of cells of `resolution` degrees in `length` degrees.
+fn cells(length: f64, resolution: f64) -> Result<u64, LandmaskError> {
+    let n = (length / resolution).round();
+
+    if resolution > 0. && n >= 1. && (n * resolution - length).abs() < 1e-9 * length {
+        Ok(n as u64)
+    } else {
+        Err(LandmaskError::InvalidInput(format!(
+            "{} degrees is not a multiple of the resolution {}",
+            length, resolution
+        )))
+    }
+}
+
+/// Where a mask comes from, stored in the mask files.
+#[cfg_attr(feature = "python", pyclass(get_all))]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MaskMetadata {
+    /// The provider of the land polygons, e.g. `gshhg` or `osm`.
+    pub provider: String,
+    /// The version of the data, e.g. `2.3.7`.
+    pub data_version: String,
+    /// The licence of the data, e.g. `LGPL-3.0`.
+    pub licence: String,
+}
+
+/// Magic number at the start of the mask files.
+const MAGIC: [u8; 8] = *b"\x89RLMASK\n";
+
+/// Version of the mask file format written by `write_to`.
+pub const FORMAT_VERSION: u32 = 1;
+
+/// Flag for mask files with the WKB of the shapes after the bitmap.
+const HAS_WKB: u32 = 1;
+
 impl RoaringMask {
-    pub fn from_compressed<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+    /// Read an xz-compressed mask file, see `read_from`. The WKB in the file is skipped.
+    pub fn from_compressed<P: AsRef<Path>>(path: P) -> Result<Self, LandmaskError> {
+        let fd = File::open(path)?;
+        let fd = io::BufReader::new(fd);
+        let fd = xz2::bufread::XzDecoder::new(fd);
+
+        RoaringMask::read(fd, false, false).map(|(mask, _)| mask)
+    }
+
+    /// Read an xz-compressed mask file along with the WKB of the shapes, if the file has it.
+    pub fn from_compressed_with_wkb<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<(Self, Option<Vec<u8>>), LandmaskError> {
         let fd = File::open(path)?;
         let fd = io::BufReader::new(fd);
         let fd = xz2::bufread::XzDecoder::new(fd);
-        let tmap = RoaringTreemap::deserialize_from(fd)?;
 
-        Ok(RoaringMask { tmap })
+        RoaringMask::read(fd, true, false)
+    }
+
+    /// Read a mask file written by `write_to`, along with the WKB of the shapes if the file has
+    /// it. The files of earlier versions, which only hold the serialized bitmap, are still read
+    /// and get the built-in grid.
+    pub fn read_from<R: Read>(r: R) -> Result<(Self, Option<Vec<u8>>), LandmaskError> {
+        RoaringMask::read(r, true, false)
+    }
+
+    /// Read a mask file, see `read_from`. The WKB is skipped unless `keep_wkb`, and the bitmap is
+    /// not validated if the file is `trusted`, like the built-in masks.
+    fn read<R: Read>(
+        mut r: R,
+        keep_wkb: bool,
+        trusted: bool,
+    ) -> Result<(Self, Option<Vec<u8>>), LandmaskError> {
+        let mut magic = [0; 8];
+        r.read_exact(&mut magic)?;
+
+        if magic != MAGIC {
+            // A bare bitmap. Its first eight bytes are the number of bitmaps in the tree, which
+            // never looks like the magic number.
+            let tmap = deserialize(io::Cursor::new(magic).chain(r), trusted)?;
+
+            return Ok((
+                RoaringMask {
+                    tmap,
+                    ..RoaringMask::default()
+                },
+                None,
+            ));
+        }
+
+        let invalid = |msg: &str| LandmaskError::InvalidData(format!("{} in mask file", msg));
+
+        let mut r = Checksum::new(r);
+        r.update(&MAGIC);
+
+        let version = read_u32(&mut r)?;
+        if version == 0 || version > FORMAT_VERSION {
+            return Err(LandmaskError::InvalidData(format!(
+                "unsupported mask file version: {}",
+                version
+            )));
+        }
+
+        let flags = read_u32(&mut r)?;
+        if flags & !HAS_WKB != 0 {
+            return Err(invalid("unknown flags"));
+        }
+
+        let nx = read_u64(&mut r)?;
+        let ny = read_u64(&mut r)?;
+        let mut t = [0.; 6];
+        for v in &mut t {
+            *v = f64::from_bits(read_u64(&mut r)?);
+        }
+        let transform = Affine {
+            sa: t[0],
+            sb: t[1],
+            sc: t[2],
+            sd: t[3],
+            se: t[4],
+            sf: t[5],
+        };
+
+        if nx == 0 || ny == 0 || nx.checked_mul(ny).is_none() {
+            return Err(invalid("invalid grid"));
+        }
+        if !t.iter().all(|v| v.is_finite()) || t[0] * t[4] - t[1] * t[3] == 0. {
+            return Err(invalid("invalid transform"));
+        }
+
+        let metadata = MaskMetadata {
+            provider: read_string(&mut r)?,
+            data_version: read_string(&mut r)?,
+            licence: read_string(&mut r)?,
+        };
+
+        let len = read_u64(&mut r)?;
+        let mut bitmap = (&mut r).take(len);
+        let tmap = deserialize(&mut bitmap, trusted)?;
+        if bitmap.limit() != 0 {
+            return Err(invalid("invalid length of the bitmap"));
+        }
+
+        let wkb = if flags & HAS_WKB != 0 {
+            let len = read_u64(&mut r)?;
+            let mut wkb = (&mut r).take(len);
+
+            let (wkb, n) = if keep_wkb {
+                let mut buf = Vec::new();
+                let n = wkb.read_to_end(&mut buf)?;
+                (Some(buf), n as u64)
+            } else {
+                (None, io::copy(&mut wkb, &mut io::sink())?)
+            };
+
+            if n != len {
+                return Err(invalid("unexpected end of the WKB"));
+            }
+
+            wkb
+        } else {
+            None
+        };
+
+        let sum = r.sum();
+        if read_u32(&mut r.inner)? != sum {
+            return Err(invalid("checksum mismatch"));
+        }
+
+        let mask = RoaringMask {
+            tmap,
+            metadata,
+            ..RoaringMask::default()
+        }
+        .with_grid(Grid { nx, ny, transform })?;
+
+        Ok((mask, wkb))
+    }
+
+    /// Write the mask file, with the WKB of the shapes if given. All numbers are
+    /// little-endian:
+    ///
+    /// * magic number `\x89RLMASK\n` (8 bytes)
+    /// * format version (u32), and flags (u32): 1 if the file has WKB
+    /// * columns and rows of the grid (2 u64), and its transform (6 f64: sa, sb, sc, sd, se, sf)
+    /// * provider, data version and licence, each as its length (u32) and UTF-8
+    /// * length of the bitmap (u64), and the serialized `RoaringTreemap`
+    /// * if the file has WKB: its length (u64), and the WKB
+    /// * CRC-32 of everything before it (u32)
+    pub fn write_to<W: Write>(&self, w: W, wkb: Option<&[u8]>) -> Result<(), LandmaskError> {
+        let mut w = Checksum::new(w);
+
+        w.write_all(&MAGIC)?;
+        w.write_all(&FORMAT_VERSION.to_le_bytes())?;
+        let flags = if wkb.is_some() { HAS_WKB } else { 0 };
+        w.write_all(&flags.to_le_bytes())?;
+
+        w.write_all(&self.grid.nx.to_le_bytes())?;
+        w.write_all(&self.grid.ny.to_le_bytes())?;
+        let t = &self.grid.transform;
+        for v in [t.sa, t.sb, t.sc, t.sd, t.se, t.sf] {
+            w.write_all(&v.to_le_bytes())?;
+        }
+
+        let m = &self.metadata;
+        for s in [&m.provider, &m.data_version, &m.licence] {
+            let len: u32 = s.len().try_into().map_err(|_| {
+                LandmaskError::InvalidInput("metadata of the mask is too long".into())
+            })?;
+            w.write_all(&len.to_le_bytes())?;
+            w.write_all(s.as_bytes())?;
+        }
+
+        w.write_all(&(self.tmap.serialized_size() as u64).to_le_bytes())?;
+        self.tmap.serialize_into(&mut w)?;
+
+        if let Some(wkb) = wkb {
+            w.write_all(&(wkb.len() as u64).to_le_bytes())?;
+            w.write_all(wkb)?;
+        }
+
+        let sum = w.sum();
+        w.inner.write_all(&sum.to_le_bytes())?;
+
+        Ok(())
+    }
+
+    /// Use `grid` for the mask, e.g. for a mask with another resolution or a regional mask read
+    /// with `from_compressed`. The mask must fit in the grid.
+    pub fn with_grid(self, grid: Grid) -> Result<Self, LandmaskError> {
+        if self.tmap.max().is_some_and(|max| max >= grid.nx * grid.ny) {
+            return Err(LandmaskError::InvalidData(
+                "the mask does not fit in the grid".into(),
+            ));
+        }
+
+        Ok(RoaringMask {
+            grid,
+            cells: OnceLock::new(),
+            ..self
+        })
+    }
+
+    /// The grid of the cells of the mask.
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// Set the metadata stored with the mask in the mask files.
+    pub fn with_metadata(self, metadata: MaskMetadata) -> Self {
+        RoaringMask { metadata, ..self }
+    }
+
+    /// Where the mask comes from. Empty for masks read from files without metadata.
+    pub fn metadata(&self) -> &MaskMetadata {
+        &self.metadata
+    }
+
+    /// Use the longitude convention for the point and segment queries, see
+    /// `RoaringLandmask::with_longitude_convention`. The bounding box queries wrap any
+    /// longitude.
+    pub fn with_longitude_convention(mut self, longitudes: LongitudeConvention) -> Self {
+        self.longitudes = longitudes;
+        self
+    }
+
+    /// The longitude convention of the point and segment queries.
+    pub fn longitude_convention(&self) -> LongitudeConvention {
+        self.longitudes
+    }
+
+    /// The longitude `x` wrapped onto [-180, 180), panicking if it is outside the convention.
+    fn longitude(&self, x: f64) -> f64 {
+        self.longitudes
+            .normalize(x)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Write the mask file xz-compressed, so that it can be read with `from_compressed`. See
+    /// `write_to`.
+    pub fn to_compressed<P: AsRef<Path>>(&self, path: P) -> Result<(), LandmaskError> {
+        self.write_compressed(path.as_ref(), None)
+    }
+
+    /// Write the mask file xz-compressed along with the WKB of the shapes, so that it can be
+    /// read with `from_compressed_with_wkb`.
+    pub fn to_compressed_with_wkb<P: AsRef<Path>>(
+        &self,
+        path: P,
+        wkb: &[u8],
+    ) -> Result<(), LandmaskError> {
+        self.write_compressed(path.as_ref(), Some(wkb))
+    }
+
+    fn write_compressed(&self, path: &Path, wkb: Option<&[u8]>) -> Result<(), LandmaskError> {
+        let fd = File::create(path)?;
+        let mut fd = xz2::write::XzEncoder::new(io::BufWriter::new(fd), 9);
+        self.write_to(&mut fd, wkb)?;
+        fd.finish()?.flush()?;
+
+        Ok(())
+    }
+
+    /// The number of land cells in the mask.
+    pub fn len(&self) -> u64 {
+        self.tmap.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tmap.is_empty()
+    }
+
+    /// The coastal cells, if they have been computed.
+    pub fn coastal_cells(&self) -> Option<&RoaringTreemap> {
+        self.cells.get().map(|c| &c.coastal)
+    }
+
+    /// The interior land cells, if they have been computed.
+    pub fn interior_cells(&self) -> Option<&RoaringTreemap> {
+        self.cells.get().map(|c| &c.interior)
+    }
+
+    /// Rasterise the land polygons in `geom` into a new mask, see `from_rings`.
+    #[cfg(feature = "geos")]
+    pub fn from_geometry<G: Geom>(
+        geom: &G,
+        resolution: f64,
+        rule: RasterRule,
+    ) -> Result<Self, LandmaskError> {
+        let mut rings = Vec::new();
+        collect_rings(geom, &mut rings)?;
+
+        RoaringMask::from_rings(rings, resolution, rule)
+    }
+
+    /// Rasterise land polygons into a new mask, with cells of `resolution` degrees. The rings
+    /// are given like for `Coastline::from_rings`, and the polygons must not overlap.
+    ///
+    /// The rows of cells are scanned at the latitude of their centres, and filled between the
+    /// crossings of the rings. With `RasterRule::AnyLand` every cell that a ring passes through,
+    /// or touches, is land as well, so that the mask never says ocean for a point on land like
+    /// the built-in masks. `RasterRule::Centre` only keeps the cells with their centre on land,
+    /// and `RasterRule::AllLand` only those entirely on land.
+    ///
+    /// The mask gets a global grid with cells of `resolution` degrees, see `Grid::global`.
+    pub fn from_rings<I>(rings: I, resolution: f64, rule: RasterRule) -> Result<Self, LandmaskError>
+    where
+        I: IntoIterator<Item = (Vec<[f64; 2]>, bool)>,
+    {
+        RoaringMask::from_rings_with_grid(rings, Grid::global(resolution)?, rule)
+    }
+
+    /// Rasterise land polygons into a new mask on `grid`, e.g. a regional grid. See
+    /// `from_rings`.
+    pub fn from_rings_with_grid<I>(
+        rings: I,
+        grid: Grid,
+        rule: RasterRule,
+    ) -> Result<Self, LandmaskError>
+    where
+        I: IntoIterator<Item = (Vec<[f64; 2]>, bool)>,
+    {
+        let mut mask = RoaringMask {
+            grid,
+            ..RoaringMask::default()
+        };
+
+        if matches!(rule, RasterRule::Majority | RasterRule::Fraction) {
+            return Err(LandmaskError::InvalidInput(format!(
+                "cannot rasterise polygons with {:?}",
+                rule
+            )));
+        }
+
+        let coast = Coastline::from_rings(rings);
+        let mut tmap = RoaringTreemap::new();
+
+        let grid = &mask.grid;
+        let nx = grid.nx;
+        let lats = (0..grid.ny).map(|row| mask.cell_centre(0, row as i64).1);
+        let mut row = 0;
+
+        coast.scan(lats, |lat, xs| {
+            for land in xs.chunks_exact(2) {
+                // The cells with their centre between the crossings, wrapped around the
+                // antimeridian for global grids.
+                let (c0, _) = grid.transform.apply(land[0], lat);
+                let (c1, _) = grid.transform.apply(land[1], lat);
+                let c0 = (c0 - 0.5).ceil() as i64;
+                let c1 = ((c1 - 0.5).floor() as i64).min(c0 + nx as i64 - 1);
+
+                if grid.wraps() && c1 >= c0 {
+                    let w0 = c0.rem_euclid(nx as i64) as u64;
+                    let w1 = w0 + (c1 - c0) as u64;
+
+                    if w1 < nx {
+                        tmap.insert_range(row * nx + w0..=row * nx + w1);
+                    } else {
+                        tmap.insert_range(row * nx + w0..row * nx + nx);
+                        tmap.insert_range(row * nx..=row * nx + w1 - nx);
+                    }
+                } else {
+                    let (c0, c1) = (c0.max(0), c1.min(nx as i64 - 1));
+                    if c1 >= c0 {
+                        tmap.insert_range(row * nx + c0 as u64..=row * nx + c1 as u64);
+                    }
+                }
+            }
+            row += 1;
+        });
+
+        let mut boundary = RoaringTreemap::new();
+        for (a, b, seam) in coast.edges() {
+            // The seams where the polygons have been cut have land on both sides.
+            if rule == RasterRule::AnyLand || !seam {
+                segment_cells(grid, a[0], a[1], b[0], b[1], 1e-6, |i| {
+                    boundary.insert(i);
+                    false
+                });
+            }
+        }
+
+        match rule {
+            RasterRule::AnyLand => tmap |= boundary,
+            RasterRule::AllLand => tmap -= boundary,
+            _ => {}
+        }
+
+        mask.tmap = tmap;
+        Ok(mask)
+    }
+
+    /// Compute the coastal and interior land cells from the coastline of the shapes the mask was
+    /// made from, unless they have already been computed.
+    ///
+    /// Every cell that an edge of the coastline passes through, or touches, is coastal. The seams
+    /// where the polygons have been cut are not. The remaining land cells with their centre on
+    /// land lie entirely inside the land polygons, and are interior.
+    pub fn init_coastal(&self, coast: &Coastline) {
+        self.cells.get_or_init(|| {
+            let mut coastal = RoaringTreemap::new();
+
+            for (a, b, seam) in coast.edges() {
+                if !seam {
+                    segment_cells(&self.grid, a[0], a[1], b[0], b[1], 1e-6, |i| {
+                        coastal.insert(i);
+                        false
+                    });
+                }
+            }
+
+            let mut interior = RoaringTreemap::new();
+            let (nx, transform) = (self.grid.nx, &self.grid.transform);
+            let lats = (0..self.grid.ny).map(|row| self.cell_centre(0, row as i64).1);
+            let mut row = 0;
+
+            coast.scan(lats, |lat, xs| {
+                for land in xs.chunks_exact(2) {
+                    // The cells with their centre between the crossings.
+                    let (c0, _) = transform.apply(land[0], lat);
+                    let (c1, _) = transform.apply(land[1], lat);
+                    let c0 = ((c0 - 0.5).ceil() as i64).max(0) as u64;
+                    let c1 = ((c1 - 0.5).floor() as i64).min(nx as i64 - 1);
+
+                    if c1 >= c0 as i64 {
+                        interior.insert_range(row * nx + c0..=row * nx + c1 as u64);
+                    }
+                }
+                row += 1;
+            });
+
+            interior -= &coastal;
+            interior &= &self.tmap;
+
+            Cells { coastal, interior }
+        });
+    }
+
+    /// The cell containing point (x, y). The x-index is not wrapped around the antimeridian.
+    pub(crate) fn cell(&self, x: f64, y: f64) -> (i64, i64) {
+        self.grid.cell(x, y)
+    }
+
+    /// The longitude and latitude of the centre of cell (x, y).
+    pub(crate) fn cell_centre(&self, x: i64, y: i64) -> (f64, f64) {
+        self.grid
+            .transform
+            .apply_inverse(x as f64 + 0.5, y as f64 + 0.5)
+    }
+
+    /// Call `f` with each row of cells covered by the bounding box, with the latitude of the row,
+    /// the number of land cells and the number of cells in the row. See `any_land`.
+    fn bbox_rows<F>(&self, x0: f64, y0: f64, x1: f64, y1: f64, mut f: F)
+    where
+        F: FnMut(f64, u64, u64),
+    {
+        assert!(y0 >= -90. && y0 <= 90.);
+        assert!(y1 >= -90. && y1 <= 90.);
+
+        let width = if x1 - x0 >= 360. {
+            360.
+        } else if x1 >= x0 {
+            x1 - x0
+        } else {
+            x1 - x0 + 360.
+        };
+
+        let x0 = super::modulate_longitude(x0);
+        let (c0, r0) = self.cell(x0, y0.min(y1));
+        let (c1, r1) = self.cell(x0 + width, y0.max(y1));
+
+        let (nx, ny) = (self.grid.nx, self.grid.ny);
+
+        let (c0, c1) = if !self.grid.wraps() {
+            (c0.max(0), c1.min(nx as i64 - 1))
+        } else if c1 - c0 + 1 >= nx as i64 {
+            (0, nx as i64 - 1)
+        } else {
+            (
+                c0.rem_euclid(nx as i64),
+                c0.rem_euclid(nx as i64) + (c1 - c0),
+            )
+        };
+
+        if c1 < c0 {
+            return;
+        }
+
+        for row in r0.max(0)..=r1.min(ny as i64 - 1) {
+            let row = row as u64;
+            let (c0, c1) = (c0 as u64, c1 as u64);
+
+            let land = if c1 < nx {
+                self.range_len(row * nx + c0, row * nx + c1)
+            } else {
+                // Crosses the antimeridian
+                self.range_len(row * nx + c0, row * nx + nx - 1)
+                    + self.range_len(row * nx, row * nx + c1 - nx)
+            };
+
+            let (_, lat) = self.cell_centre(0, row as i64);
+            f(lat, land, c1 - c0 + 1);
+        }
+    }
+
+    /// Number of land cells in the range of indices [a, b].
+    fn range_len(&self, a: u64, b: u64) -> u64 {
+        if a > 0 {
+            self.tmap.rank(b) - self.tmap.rank(a - 1)
+        } else {
+            self.tmap.rank(b)
+        }
+    }
+
+    /// Check if cell (x, y) is land, the x-index is wrapped around the antimeridian for global
+    /// grids. Cells beyond the poles, or outside the grid, are never land.
+    pub(crate) fn contains_cell(&self, x: i64, y: i64) -> bool {
+        self.grid.index(x, y).is_some_and(|i| self.tmap.contains(i))
+    }
+
+    /// Classify the cell containing point (x, y), which must be in a land cell. See `classify`.
+    pub(crate) fn classify_land_unchecked(&self, x: f64, y: f64) -> CellClass {
+        if self.interior_unchecked(x, y) {
+            CellClass::Land
+        } else {
+            CellClass::Coastal
+        }
+    }
+
+    /// Check if point (x, y) is in an interior land cell, does not check for bounds. Without the
+    /// interior cells no point is.
+    pub(crate) fn interior_unchecked(&self, x: f64, y: f64) -> bool {
+        match self.cells.get() {
+            Some(cells) => {
+                let (x, y) = self.cell(x, y);
+
+                self.grid
+                    .index(x, y)
+                    .is_some_and(|i| cells.interior.contains(i))
+            }
+            None => false,
+        }
     }
 }
 
-#[pymethods]
+/// Call `f` with the index of every cell the segment from (x0, y0) to (x1, y1) passes through,
+/// until `f` returns `true`. Cells within `pad` cells of the segment are included as well.
+///
+/// `x1` may be outside [-180, 180] for segments crossing the antimeridian. The cells outside the
+/// grid are skipped.
+///
+/// Returns `true` if `f` did.
+fn segment_cells<F>(grid: &Grid, x0: f64, y0: f64, x1: f64, y1: f64, pad: f64, mut f: F) -> bool
+where
+    F: FnMut(u64) -> bool,
+{
+    let (fx0, fy0) = grid.transform.apply(x0, y0);
+    let (fx1, fy1) = grid.transform.apply(x1, y1);
+
+    let (ylo, yhi) = (fy0.min(fy1), fy0.max(fy1));
+    let row0 = ((ylo - pad).floor() as i64).max(0);
+    let row1 = ((yhi + pad).floor() as i64).min(grid.ny as i64 - 1);
+
+    for row in row0..=row1 {
+        // The part of the segment within this row of cells.
+        let (cx0, cx1) = if fy0 == fy1 {
+            (fx0, fx1)
+        } else {
+            let at = |y: f64| fx0 + (fx1 - fx0) * (y - fy0) / (fy1 - fy0);
+            (
+                at((row as f64).clamp(ylo, yhi)),
+                at(((row + 1) as f64).clamp(ylo, yhi)),
+            )
+        };
+
+        let col0 = (cx0.min(cx1) - pad).floor() as i64;
+        let col1 = (cx0.max(cx1) + pad).floor() as i64;
+
+        for col in col0..=col1 {
+            if let Some(i) = grid.index(col, row) {
+                if f(i) {
+                    return true;
+                }
+            }
+        }
+    }
+
+    false
+}
+
 impl RoaringMask {
-    #[staticmethod]
     /// Make a new mask.
-    pub fn new(provider: LandmaskProvider) -> io::Result<Self> {
+    pub fn new(provider: LandmaskProvider) -> Result<Self, LandmaskError> {
         use crate::GsshgData;
         use crate::OsmData;
 
-        let buf = match provider {
-            LandmaskProvider::Gshhg => GsshgData::get("gshhg_mask.tbmap.xz"),
-            LandmaskProvider::Osm => OsmData::get("osm_mask.tbmap.xz"),
-        }
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cannot find mask"))?;
+        let (buf, provider, licence) = match provider {
+            LandmaskProvider::Gshhg => (GsshgData::get("gshhg_mask.tbmap.xz"), "gshhg", "LGPL-3.0"),
+            LandmaskProvider::Osm => (OsmData::get("osm_mask.tbmap.xz"), "osm", "ODbL-1.0"),
+        };
+        let buf = buf.ok_or_else(|| LandmaskError::InvalidData("cannot find mask".into()))?;
 
         let buf: &[u8] = buf.data.borrow();
 
         let fd = xz2::read::XzDecoder::new(buf);
-        let tmap = RoaringTreemap::deserialize_unchecked_from(fd)?;
-
-        Ok(RoaringMask { tmap })
-    }
+        let (mask, _) = RoaringMask::read(fd, false, true)?;
 
-    #[getter]
-    pub fn dx(&self) -> f64 {
-        (180f64 - (-180f64)) / (NX as f64)
-    }
-
-    #[getter]
-    pub fn dy(&self) -> f64 {
-        (180f64 - (-180f64)) / (NX as f64)
+        // The built-in masks may be bare bitmaps, without the metadata.
+        if mask.metadata.provider.is_empty() {
+            Ok(mask.with_metadata(MaskMetadata {
+                provider: provider.into(),
+                data_version: String::new(),
+                licence: licence.into(),
+            }))
+        } else {
+            Ok(mask)
+        }
     }
 
     /// Check if point (x, y) is on land.
@@ -125,63 +971,468 @@ impl RoaringMask {
     ///
     /// Returns `true` if the point is on land or close to the shore.
     pub fn contains(&self, x: f64, y: f64) -> bool {
-        let x = super::modulate_longitude(x);
+        let x = self.longitude(x);
         debug_assert!(x >= -180. && x <= 180.);
         assert!(y >= -90.);
 
-        let (x, y) = TRANSFORM.apply(x, y);
-        let x = x as u64;
-        let y = y as u64;
+        self.contains_unchecked(x, y)
+    }
+
+    /// Same as `contains`, but returns an error for NaN or infinite coordinates, latitudes
+    /// outside [-90, 90] and longitudes outside the longitude convention instead of panicking.
+    pub fn try_contains(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        check_coordinates(x, y)?;
+        self.longitudes.normalize(x)?;
 
-        // Special case where we are in northernmost cell. North Pole is always in ocean anyway.
-        if y == NY {
-            return false;
+        Ok(self.contains(x, y))
+    }
+
+    /// Check if the segment from (x0, y0) to (x1, y1) passes through any land cell.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// The segment goes the shortest way around the Earth, so a segment from 179 to -179 crosses
+    /// the antimeridian. Like `contains` the check is _optimistic_, the positive segments should
+    /// be checked against the vectorized land shapes.
+    pub fn intersects_segment(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        assert!(y0 >= -90. && y0 <= 90.);
+        assert!(y1 >= -90. && y1 <= 90.);
+
+        let x0 = self.longitude(x0);
+        let x1 = x0 + (self.longitude(x1) - x0 + 180.).rem_euclid(360.) - 180.;
+
+        self.intersects_segment_unchecked(x0, y0, x1, y1)
+    }
+
+    /// Same as `intersects_segment`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_intersects_segment(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<bool, LandmaskError> {
+        check_coordinates(x0, y0)?;
+        check_coordinates(x1, y1)?;
+        self.longitudes.normalize(x0)?;
+        self.longitudes.normalize(x1)?;
+
+        Ok(self.intersects_segment(x0, y0, x1, y1))
+    }
+
+    /// Classify the cell containing point (x, y) as ocean, land or coastal.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// Only points in `Coastal` cells need to be checked against the vectorized land shapes.
+    /// Before the coastal cells have been computed (see `init_coastal`) every land cell is
+    /// `Coastal`.
+    pub fn classify(&self, x: f64, y: f64) -> CellClass {
+        assert!(y >= -90. && y <= 90.);
+
+        self.classify_unchecked(self.longitude(x), y)
+    }
+
+    /// Same as `classify`, but does not check for bounds. `x` must be in [-180, 180).
+    pub(crate) fn classify_unchecked(&self, x: f64, y: f64) -> CellClass {
+        if self.contains_unchecked(x, y) {
+            self.classify_land_unchecked(x, y)
+        } else {
+            CellClass::Ocean
         }
+    }
+
+    /// Same as `classify`, but returns an error for invalid coordinates instead of panicking, see
+    /// `try_contains`.
+    pub fn try_classify(&self, x: f64, y: f64) -> Result<CellClass, LandmaskError> {
+        check_coordinates(x, y)?;
+        self.longitudes.normalize(x)?;
+
+        Ok(self.classify(x, y))
+    }
+
+    /// Check if there is any land in the bounding box with lower left corner (x0, y0) and upper
+    /// right corner (x1, y1).
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// Boxes with `x0` greater than `x1` cross the antimeridian. Like `contains` the check is
+    /// _optimistic_, cells close to the shore count as land.
+    pub fn any_land(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        let mut any = false;
+        self.bbox_rows(x0, y0, x1, y1, |_, land, _| any |= land > 0);
+        any
+    }
+
+    /// Same as `any_land`, but returns an error for NaN or infinite corners and latitudes
+    /// outside [-90, 90] instead of panicking.
+    pub fn try_any_land(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Result<bool, LandmaskError> {
+        check_coordinates(x0, y0)?;
+        check_coordinates(x1, y1)?;
+
+        Ok(self.any_land(x0, y0, x1, y1))
+    }
+
+    /// Check if the bounding box is all ocean. See `any_land`.
+    pub fn all_ocean(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        !self.any_land(x0, y0, x1, y1)
+    }
+
+    /// Same as `all_ocean`, but returns an error for NaN or infinite corners and latitudes
+    /// outside [-90, 90] instead of panicking.
+    pub fn try_all_ocean(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Result<bool, LandmaskError> {
+        check_coordinates(x0, y0)?;
+        check_coordinates(x1, y1)?;
+
+        Ok(self.all_ocean(x0, y0, x1, y1))
+    }
+
+    /// Fraction of the area of the bounding box that is covered by land cells. The rows of
+    /// cells are weighted by their area. See `any_land`.
+    pub fn land_fraction(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> f64 {
+        let mut land = 0.;
+        let mut total = 0.;
+
+        self.bbox_rows(x0, y0, x1, y1, |lat, l, n| {
+            let w = lat.to_radians().cos();
+            land += w * l as f64;
+            total += w * n as f64;
+        });
+
+        if total > 0. {
+            land / total
+        } else {
+            0.
+        }
+    }
+
+    /// Same as `land_fraction`, but returns an error for NaN or infinite corners and latitudes
+    /// outside [-90, 90] instead of panicking.
+    pub fn try_land_fraction(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<f64, LandmaskError> {
+        check_coordinates(x0, y0)?;
+        check_coordinates(x1, y1)?;
+
+        Ok(self.land_fraction(x0, y0, x1, y1))
+    }
+
+    pub fn dx(&self) -> f64 {
+        self.grid.dx()
+    }
+
+    pub fn dy(&self) -> f64 {
+        self.grid.dy()
+    }
+}
+
+#[cfg_attr(feature = "python", pymethods)]
+impl RoaringMask {
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "new")]
+    fn py_new(provider: LandmaskProvider) -> Result<Self, LandmaskError> {
+        RoaringMask::new(provider)
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "dx")]
+    fn py_dx(&self) -> f64 {
+        self.dx()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "dy")]
+    fn py_dy(&self) -> f64 {
+        self.dy()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "grid")]
+    fn py_grid(&self) -> Grid {
+        self.grid.clone()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "transform")]
+    fn py_transform(&self) -> Affine {
+        self.grid.transform.clone()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "metadata")]
+    fn py_metadata(&self) -> MaskMetadata {
+        self.metadata.clone()
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "longitude_convention")]
+    fn py_longitude_convention(&self) -> LongitudeConvention {
+        self.longitudes
+    }
+
+    #[cfg(feature = "python")]
+    #[setter]
+    #[pyo3(name = "longitude_convention")]
+    fn py_set_longitude_convention(&mut self, longitudes: LongitudeConvention) {
+        self.longitudes = longitudes;
+    }
+
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "from_compressed")]
+    fn py_from_compressed(path: std::path::PathBuf) -> Result<Self, LandmaskError> {
+        RoaringMask::from_compressed(path)
+    }
 
-        debug_assert!(x < NX);
-        assert!(y < NY);
+    #[cfg(feature = "python")]
+    #[pyo3(name = "to_compressed")]
+    fn py_to_compressed(&self, path: std::path::PathBuf) -> Result<(), LandmaskError> {
+        self.to_compressed(path)
+    }
 
-        self.tmap.contains(y * NX + x)
+    #[cfg(feature = "python")]
+    #[pyo3(name = "contains")]
+    fn py_contains(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        self.try_contains(x, y)
     }
 
     /// Same as `contains`, but does not check for bounds.
+    ///
+    /// In the built-in grid the first column is centred on -180, and also holds the longitudes
+    /// just west of 180. The northernmost row is centred on the North Pole, and is never land.
     pub(crate) fn contains_unchecked(&self, x: f64, y: f64) -> bool {
-        let (x, y) = TRANSFORM.apply(x, y);
-        let x = x as u64;
-        let y = y as u64;
-        self.tmap.contains(y * NX + x)
+        let (x, y) = self.cell(x, y);
+        self.contains_cell(x, y)
     }
 
-    pub fn contains_many(
+    #[cfg(feature = "python")]
+    #[pyo3(name = "intersects_segment")]
+    fn py_intersects_segment(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<bool, LandmaskError> {
+        self.try_intersects_segment(x0, y0, x1, y1)
+    }
+
+    /// Same as `intersects_segment`, but does not check for bounds or wrap the segment. `x1` may
+    /// be outside [-180, 180] for segments crossing the antimeridian.
+    pub(crate) fn intersects_segment_unchecked(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        segment_cells(&self.grid, x0, y0, x1, y1, 0., |i| self.tmap.contains(i))
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "classify")]
+    fn py_classify(&self, x: f64, y: f64) -> Result<CellClass, LandmaskError> {
+        self.try_classify(x, y)
+    }
+
+    #[cfg(feature = "python")]
+    pub fn classify_many(
         &self,
         py: Python,
         x: PyReadonlyArrayDyn<f64>,
         y: PyReadonlyArrayDyn<f64>,
-    ) -> Py<PyArray<bool, numpy::Ix1>> {
+    ) -> Result<Py<PyArray<u8, numpy::Ix1>>, LandmaskError> {
         let x = x.as_array();
         let y = y.as_array();
 
-        PyArray::from_iter(
-            py,
-            x.iter().zip(y.iter()).map(|(x, y)| self.contains(*x, *y)),
-        )
-        .to_owned()
+        let classes = x
+            .iter()
+            .zip(y.iter())
+            .map(|(x, y)| self.try_classify(*x, *y).map(|c| c as u8))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(PyArray::from_vec(py, classes).to_owned())
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "any_land")]
+    fn py_any_land(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Result<bool, LandmaskError> {
+        self.try_any_land(x0, y0, x1, y1)
     }
 
+    #[cfg(feature = "python")]
+    #[pyo3(name = "all_ocean")]
+    fn py_all_ocean(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Result<bool, LandmaskError> {
+        self.try_all_ocean(x0, y0, x1, y1)
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "land_fraction")]
+    fn py_land_fraction(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> Result<f64, LandmaskError> {
+        self.try_land_fraction(x0, y0, x1, y1)
+    }
+
+    /// Check many points, see `contains`. Invalid points raise an error, or are treated as ocean
+    /// or land, depending on `invalid`.
+    ///
+    /// The coordinates may be float64, float32, int64 or int32 arrays, and are multiplied by
+    /// `scale`, e.g. `1e-7` for integers in units of 1e-7 degrees.
+    ///
+    /// The results are written to `out` if it is given, which must have the same size as `x`.
+    /// The GIL is released while the points are checked.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, invalid = InvalidPolicy::Raise, scale = 1., out = None))]
+    pub fn contains_many(
+        &self,
+        py: Python,
+        x: PyCoords,
+        y: PyCoords,
+        invalid: InvalidPolicy,
+        scale: f64,
+        out: Option<&PyArrayDyn<bool>>,
+    ) -> Result<PyObject, LandmaskError> {
+        crate::batch::contains_many_py(self, py, x, y, out, scale, invalid)
+    }
+
+    /// Check many points, giving `1` for land, `0` for ocean and `-1` for invalid points, e.g. NaN
+    /// or latitudes outside [-90, 90]. The result has the same shape as `x` and `y`.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, scale = 1.))]
+    pub fn contains_many_i8(
+        &self,
+        py: Python,
+        x: PyCoords,
+        y: PyCoords,
+        scale: f64,
+    ) -> Result<Py<PyArray<i8, numpy::IxDyn>>, LandmaskError> {
+        crate::batch::contains_many_i8_py(self, py, x, y, scale)
+    }
+
+    /// Check many points in parallel, see `contains_many`.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, invalid = InvalidPolicy::Raise, scale = 1., out = None))]
     pub fn contains_many_par(
         &self,
         py: Python,
-        x: PyReadonlyArrayDyn<f64>,
-        y: PyReadonlyArrayDyn<f64>,
-    ) -> Py<PyArray<bool, numpy::IxDyn>> {
-        let x = x.as_array();
-        let y = y.as_array();
+        x: PyCoords,
+        y: PyCoords,
+        invalid: InvalidPolicy,
+        scale: f64,
+        out: Option<&PyArrayDyn<bool>>,
+    ) -> Result<PyObject, LandmaskError> {
+        crate::batch::contains_many_par_py(self, py, x, y, out, scale, invalid)
+    }
+}
+
+/// Deserialize the bitmap, without validating it if it is `trusted`.
+fn deserialize<R: Read>(r: R, trusted: bool) -> io::Result<RoaringTreemap> {
+    if trusted {
+        RoaringTreemap::deserialize_unchecked_from(r)
+    } else {
+        RoaringTreemap::deserialize_from(r)
+    }
+}
+
+fn read_u32<R: Read>(r: &mut R) -> io::Result<u32> {
+    let mut b = [0; 4];
+    r.read_exact(&mut b)?;
+    Ok(u32::from_le_bytes(b))
+}
+
+fn read_u64<R: Read>(r: &mut R) -> io::Result<u64> {
+    let mut b = [0; 8];
+    r.read_exact(&mut b)?;
+    Ok(u64::from_le_bytes(b))
+}
+
+fn read_string<R: Read>(r: &mut R) -> Result<String, LandmaskError> {
+    let len = read_u32(r)?;
+    let mut s = String::new();
+
+    if r.take(len as u64).read_to_string(&mut s)? != len as usize {
+        return Err(LandmaskError::InvalidData(
+            "unexpected end of metadata in mask file".into(),
+        ));
+    }
 
-        use ndarray::Zip;
-        let contains = Zip::from(&x)
-            .and(&y)
-            .par_map_collect(|x, y| self.contains(*x, *y));
-        PyArray::from_owned_array(py, contains).to_owned()
+    Ok(s)
+}
+
+/// CRC-32 lookup table, for the polynomial of zlib and PNG.
+const CRC_TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 == 1 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+
+    table
+}
+
+/// Computes the CRC-32 of everything read from, or written to, `inner`.
+struct Checksum<T> {
+    inner: T,
+    crc: u32,
+}
+
+impl<T> Checksum<T> {
+    fn new(inner: T) -> Self {
+        Checksum {
+            inner,
+            crc: 0xffff_ffff,
+        }
+    }
+
+    fn update(&mut self, buf: &[u8]) {
+        for b in buf {
+            self.crc = CRC_TABLE[((self.crc ^ *b as u32) & 0xff) as usize] ^ (self.crc >> 8);
+        }
+    }
+
+    fn sum(&self) -> u32 {
+        !self.crc
+    }
+}
+
+impl<R: Read> Read for Checksum<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl<W: Write> Write for Checksum<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
     }
 }
 
@@ -200,6 +1451,19 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_new_metadata() {
+        for (provider, name) in [
+            (LandmaskProvider::Gshhg, "gshhg"),
+            (LandmaskProvider::Osm, "osm"),
+        ] {
+            let mask = RoaringMask::new(provider).unwrap();
+            assert_eq!(mask.metadata().provider, name);
+            assert!(!mask.metadata().licence.is_empty());
+            assert_eq!(mask.grid(), &Grid::default());
+        }
+    }
+
     #[test]
     fn test_np() {
         for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
@@ -216,6 +1480,394 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_intersects_segment() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringMask::new(provider).unwrap();
+
+            // Across southern Norway, and along the Norwegian sea.
+            assert!(mask.intersects_segment(5., 60., 12., 60.));
+            assert!(!mask.intersects_segment(0., 66., 2., 68.));
+
+            // Ends on land.
+            assert!(mask.intersects_segment(5., 65.6, 15., 65.6));
+
+            // Across the antimeridian, in the ocean and through Fiji.
+            assert!(!mask.intersects_segment(179.5, 0., -179.5, 0.));
+            assert!(mask.intersects_segment(178., -16.5, -179.5, -16.5));
+        }
+    }
+
+    #[test]
+    fn test_bbox() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let mask = RoaringMask::new(provider).unwrap();
+
+            // Norwegian sea, and southern Norway.
+            assert!(mask.all_ocean(0., 66., 2., 68.));
+            assert_eq!(mask.land_fraction(0., 66., 2., 68.), 0.);
+            assert!(mask.any_land(8., 60., 10., 61.));
+            assert!(mask.land_fraction(8., 60., 10., 61.) > 0.9);
+
+            let f = mask.land_fraction(0., 58., 12., 62.);
+            assert!(f > 0.1 && f < 0.9);
+
+            // Across the antimeridian, in the ocean and over Fiji.
+            assert!(mask.all_ocean(179.5, 0., -179.5, 1.));
+            assert!(mask.any_land(179., -17., -179.5, -16.));
+            assert!(!mask.all_ocean(178., -17., 181., -16.));
+
+            // The whole Earth.
+            let f = mask.land_fraction(-180., -90., 180., 90.);
+            assert!(f > 0.25 && f < 0.4);
+        }
+    }
+
+    #[test]
+    fn test_classify() {
+        // A 1 by 1 degree island.
+        let mut mask = RoaringMask::default();
+        let (c0, r0) = mask.cell(10., 10.);
+        let (c1, r1) = mask.cell(11., 11.);
+        for r in r0..=r1 {
+            let r = r as u64;
+            mask.tmap
+                .insert_range(r * NX + c0 as u64..=r * NX + c1 as u64);
+        }
+
+        assert_eq!(mask.classify(10.5, 10.5), CellClass::Coastal);
+
+        let coast = Coastline::from_rings(vec![(
+            vec![[10., 10.], [11., 10.], [11., 11.], [10., 11.], [10., 10.]],
+            true,
+        )]);
+        mask.init_coastal(&coast);
+
+        assert_eq!(mask.classify(10.5, 10.5), CellClass::Land);
+        assert_eq!(mask.classify(10.001, 10.5), CellClass::Coastal);
+        assert_eq!(mask.classify(10.5, 10.999), CellClass::Coastal);
+        assert_eq!(mask.classify(12., 10.5), CellClass::Ocean);
+        assert_eq!(mask.coastal_cells().unwrap().len(), 4 * (c1 - c0) as u64);
+        assert_eq!(
+            mask.interior_cells().unwrap().len(),
+            ((c1 - c0 - 1) * (r1 - r0 - 1)) as u64
+        );
+    }
+
+    #[test]
+    fn test_from_rings() {
+        // An island with a lake, not aligned with the cells.
+        let island = vec![
+            (
+                vec![
+                    [9.999, 10.001],
+                    [10.999, 10.001],
+                    [10.999, 10.999],
+                    [9.999, 10.999],
+                    [9.999, 10.001],
+                ],
+                true,
+            ),
+            (
+                vec![
+                    [10.4, 10.4],
+                    [10.4, 10.6],
+                    [10.6, 10.6],
+                    [10.6, 10.4],
+                    [10.4, 10.4],
+                ],
+                false,
+            ),
+        ];
+        let inside = |x: f64, y: f64| {
+            (9.999..10.999).contains(&x)
+                && (10.001..10.999).contains(&y)
+                && !((10.4..=10.6).contains(&x) && (10.4..=10.6).contains(&y))
+        };
+
+        let any = RoaringMask::from_rings(island.clone(), 1. / 240., RasterRule::AnyLand).unwrap();
+        let centre =
+            RoaringMask::from_rings(island.clone(), 1. / 240., RasterRule::Centre).unwrap();
+        let all = RoaringMask::from_rings(island, 1. / 240., RasterRule::AllLand).unwrap();
+
+        for mask in [&any, &centre, &all] {
+            assert!(mask.contains(10.5, 10.2));
+            assert!(!mask.contains(10.5, 10.5));
+            assert!(!mask.contains(12., 10.5));
+        }
+
+        // The cells on the coast, with their centres in the ocean and on land.
+        assert!(any.contains(11., 10.5));
+        assert!(!centre.contains(11., 10.5));
+        assert!(any.contains(9.998, 10.5));
+        assert!(centre.contains(9.998, 10.5));
+        assert!(!all.contains(9.998, 10.5));
+        assert!(!any.contains(9.997, 10.5));
+
+        // A point on land is never in the ocean.
+        for i in 0..=400 {
+            for j in 0..=400 {
+                let (x, y) = (9.9 + i as f64 * 0.003, 9.9 + j as f64 * 0.003);
+                if inside(x, y) {
+                    assert!(any.contains(x, y), "({}, {})", x, y);
+                }
+            }
+        }
+
+        // An island smaller than a cell.
+        let tiny = vec![(
+            vec![
+                [20.0001, 20.0001],
+                [20.0002, 20.0001],
+                [20.0002, 20.0002],
+                [20.0001, 20.0001],
+            ],
+            true,
+        )];
+        let mask = RoaringMask::from_rings(tiny, 1. / 240., RasterRule::AnyLand).unwrap();
+        assert!(mask.contains(20.00015, 20.00012));
+        assert_eq!(mask.tmap.len(), 1);
+    }
+
+    #[test]
+    fn test_compressed_roundtrip() {
+        let rings = vec![(vec![[0., 0.], [1., 0.], [1., 1.], [0., 0.]], true)];
+        let mask = RoaringMask::from_rings(rings, 1. / 240., RasterRule::AnyLand).unwrap();
+
+        let path = std::env::temp_dir().join("roaring_landmask_test_mask.tbmap.xz");
+        mask.to_compressed(&path).unwrap();
+        let read = RoaringMask::from_compressed(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(!read.is_empty());
+        assert_eq!(read.len(), mask.len());
+        assert_eq!(read.tmap, mask.tmap);
+    }
+
+    #[test]
+    fn test_longitude_convention() {
+        let rings = vec![(
+            vec![[170., 0.], [180., 0.], [180., 1.], [170., 1.], [170., 0.]],
+            true,
+        )];
+        let mask = RoaringMask::from_rings(rings, 1. / 240., RasterRule::AnyLand)
+            .unwrap()
+            .with_longitude_convention(LongitudeConvention::Positive);
+
+        assert!(mask.try_contains(175., 0.5).unwrap());
+        assert!(mask.try_contains(-185., 0.5).is_err());
+        assert!(mask.try_intersects_segment(160., 0.5, 190., 0.5).unwrap());
+        assert!(mask.try_classify(-175., 0.5).is_err());
+
+        let mask = mask.with_longitude_convention(LongitudeConvention::Signed);
+        assert!(mask.try_contains(-185., 0.5).is_err());
+        assert!(mask.try_intersects_segment(160., 0.5, 190., 0.5).is_err());
+        assert!(mask.try_intersects_segment(160., 0.5, -170., 0.5).unwrap());
+    }
+
+    #[test]
+    fn test_mask_file() {
+        let rings = vec![(vec![[0., 0.], [1., 0.], [1., 1.], [0., 0.]], true)];
+        let grid = Grid::regional(-1., -1., 2., 2., 0.01).unwrap();
+        let metadata = MaskMetadata {
+            provider: "test".into(),
+            data_version: "1.0".into(),
+            licence: "MIT".into(),
+        };
+        let mask = RoaringMask::from_rings_with_grid(rings, grid, RasterRule::AnyLand)
+            .unwrap()
+            .with_metadata(metadata.clone());
+
+        let mut buf = Vec::new();
+        mask.write_to(&mut buf, Some(b"wkb")).unwrap();
+        assert_eq!(&buf[..8], &MAGIC);
+
+        let (read, wkb) = RoaringMask::read_from(&buf[..]).unwrap();
+        assert_eq!(read.tmap, mask.tmap);
+        assert_eq!(read.grid(), mask.grid());
+        assert_eq!(read.metadata(), &metadata);
+        assert_eq!(wkb.as_deref(), Some(&b"wkb"[..]));
+        assert!(read.contains(0.5, 0.25));
+        assert!(!read.contains(0.25, 0.5));
+
+        let (_, wkb) = RoaringMask::read(&buf[..], false, false).unwrap();
+        assert_eq!(wkb, None);
+
+        // Corrupt data, a newer version and a truncated file.
+        let mut corrupt = buf.clone();
+        corrupt[100] ^= 1;
+        assert!(RoaringMask::read_from(&corrupt[..]).is_err());
+
+        let mut newer = buf.clone();
+        newer[8] = 2;
+        assert!(RoaringMask::read_from(&newer[..]).is_err());
+
+        assert!(RoaringMask::read_from(&buf[..buf.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_bare_mask_file() {
+        let rings = vec![(vec![[0., 0.], [1., 0.], [1., 1.], [0., 0.]], true)];
+        let mask = RoaringMask::from_rings(rings, 1. / 240., RasterRule::AnyLand).unwrap();
+
+        let path = std::env::temp_dir().join("roaring_landmask_test_bare_mask.tbmap.xz");
+        {
+            let fd = File::create(&path).unwrap();
+            let mut fd = xz2::write::XzEncoder::new(fd, 6);
+            mask.tmap.serialize_into(&mut fd).unwrap();
+            fd.finish().unwrap();
+        }
+
+        let (read, wkb) = RoaringMask::from_compressed_with_wkb(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(read.tmap, mask.tmap);
+        assert_eq!(read.grid(), &Grid::default());
+        assert_eq!(read.metadata(), &MaskMetadata::default());
+        assert_eq!(wkb, None);
+    }
+
+    #[test]
+    fn test_crc() {
+        let mut c = Checksum::new(io::sink());
+        c.write_all(b"123456789").unwrap();
+        assert_eq!(c.sum(), 0xcbf4_3926);
+    }
+
+    #[test]
+    fn test_from_rings_antimeridian() {
+        let rings = vec![
+            (
+                vec![
+                    [170., -10.],
+                    [180., -10.],
+                    [180., 10.],
+                    [170., 10.],
+                    [170., -10.],
+                ],
+                true,
+            ),
+            (
+                vec![
+                    [-180., -10.],
+                    [-170., -10.],
+                    [-170., 10.],
+                    [-180., 10.],
+                    [-180., -10.],
+                ],
+                true,
+            ),
+        ];
+
+        for rule in [RasterRule::AnyLand, RasterRule::Centre, RasterRule::AllLand] {
+            let mask = RoaringMask::from_rings(rings.clone(), 1. / 240., rule).unwrap();
+
+            for x in [179.999, 180., -180., -179.999, 175., -175.] {
+                assert!(mask.contains(x, 0.), "{:?}: {}", rule, x);
+            }
+            assert!(!mask.contains(-169., 0.));
+            assert!(!mask.contains(169., 0.));
+        }
+    }
+
+    #[test]
+    fn test_from_rings_invalid() {
+        let rings = vec![(vec![[0., 0.], [1., 0.], [1., 1.], [0., 0.]], true)];
+
+        assert!(matches!(
+            RoaringMask::from_rings(rings.clone(), 0.7, RasterRule::AnyLand),
+            Err(LandmaskError::InvalidInput(_))
+        ));
+        assert!(matches!(
+            RoaringMask::from_rings(rings, 1. / 240., RasterRule::Fraction),
+            Err(LandmaskError::InvalidInput(_))
+        ));
+    }
+
+    #[test]
+    fn test_grid() {
+        let grid = Grid::default();
+        assert_eq!(grid, Grid::global(1. / 240.).unwrap());
+        assert_eq!((grid.dx(), grid.dy()), (1. / 240., 1. / 240.));
+        assert!(grid.wraps());
+        assert_eq!(grid.cell(-180., -90.), (0, 0));
+        assert_eq!(grid.index(-1, 0), Some(NX - 1));
+        assert_eq!(grid.index(0, NY as i64), None);
+
+        let grid = Grid::global(0.5).unwrap();
+        assert_eq!((grid.nx, grid.ny), (720, 360));
+        assert_eq!(grid.extent(), (-180.25, -90.25, 179.75, 89.75));
+
+        let grid = Grid::regional(5., 55., 30., 72., 0.25).unwrap();
+        assert_eq!((grid.nx, grid.ny), (100, 68));
+        assert_eq!(grid.extent(), (5., 55., 30., 72.));
+        assert!(!grid.wraps());
+        assert_eq!(grid.cell(5.1, 71.9), (0, 67));
+        assert_eq!(grid.index(-1, 0), None);
+        assert_eq!(grid.index(100, 0), None);
+
+        assert!(Grid::global(0.7).is_err());
+        assert!(Grid::regional(5., 55., 30., 72., 0.3).is_err());
+        assert!(Grid::regional(30., 55., 5., 72., 0.25).is_err());
+    }
+
+    #[test]
+    fn test_from_rings_grid() {
+        let island = vec![(
+            vec![[10., 10.], [11., 10.], [11., 11.], [10., 11.], [10., 10.]],
+            true,
+        )];
+
+        let mask = RoaringMask::from_rings(island.clone(), 1. / 480., RasterRule::Centre).unwrap();
+        assert_eq!((mask.dx(), mask.dy()), (1. / 480., 1. / 480.));
+        assert_eq!(mask.grid().nx, 172800);
+        // The centres of the cells are on the meridians at the edges of the island.
+        assert_eq!(mask.len(), 481 * 480);
+        assert!(mask.contains(10.5, 10.5));
+        assert!(mask.contains(10.997, 10.997));
+        assert!(!mask.contains(11.002, 10.5));
+
+        // A regional grid, only partly covering the island.
+        let grid = Grid::regional(10.5, 0., 20., 10.5, 0.1).unwrap();
+        let mask = RoaringMask::from_rings_with_grid(island, grid, RasterRule::AnyLand).unwrap();
+        // The edges of the island are on the edges of the cells, so the cells on both sides of
+        // them count as land. The western edge is outside the grid.
+        assert_eq!(mask.len(), 6 * 6);
+        assert!(mask.contains(10.8, 10.2));
+        assert!(!mask.contains(10.8, 10.6));
+        assert!(!mask.contains(10.2, 10.2));
+        assert!(!mask.contains(-170., 0.));
+        assert!(mask.intersects_segment(10.2, 10.2, 10.8, 10.2));
+        assert!(mask.any_land(10.6, 10.1, 10.9, 10.4));
+        assert!(!mask.any_land(10., 10.6, 10.4, 11.));
+        assert!(!mask.any_land(-170., -10., -160., 10.));
+
+        let tmap = mask.tmap.clone();
+        let small = Grid::regional(10.5, 0., 11., 0.5, 0.1).unwrap();
+        assert!(mask.with_grid(small).is_err());
+
+        let mask = RoaringMask {
+            tmap,
+            ..RoaringMask::default()
+        };
+        assert_eq!(mask.grid(), &Grid::default());
+    }
+
+    #[cfg(feature = "geos")]
+    #[test]
+    fn test_from_geometry() {
+        let geom = geos::Geometry::new_from_wkt(
+            "MULTIPOLYGON(((10 10, 11 10, 11 11, 10 11, 10 10)), ((20 20, 21 20, 21 21, 20 20)))",
+        )
+        .unwrap();
+        let mask = RoaringMask::from_geometry(&geom, 1. / 240., RasterRule::AnyLand).unwrap();
+
+        assert!(mask.contains(10.5, 10.5));
+        assert!(mask.contains(20.9, 20.1));
+        assert!(!mask.contains(20.1, 20.9));
+        assert!(!mask.contains(15., 15.));
+    }
+
     #[cfg(feature = "nightly")]
     mod benches {
         use super::*;
@@ -240,8 +1892,7 @@ mod tests {
 
         #[bench]
         fn test_inv_transform(be: &mut Bencher) {
-            // let a = Affine::make();
-            let a = &TRANSFORM;
+            let a = Affine::make();
 
             let b = a.apply(40.5, 87.);
             println!("{:?}", b);
@@ -252,6 +1903,8 @@ mod tests {
             assert_eq!(c.0, 43200.5);
             assert_eq!(c.1, 28800.5);
 
+            assert_eq!(a.apply_inverse(52920.5, 42480.5), (40.5, 87.));
+
             be.iter(|| a.apply(40.5, 87.))
         }
 
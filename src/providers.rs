@@ -1,6 +1,7 @@
+#[cfg(feature = "python")]
 use pyo3::prelude::*;
 
-#[pyclass]
+#[cfg_attr(feature = "python", pyclass)]
 #[derive(Debug, Clone, Copy)]
 pub enum LandmaskProvider {
     Gshhg,
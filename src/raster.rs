@@ -0,0 +1,18 @@
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+
+/// Rule for when a cell of a raster is land, see `RoaringLandmask::rasterize`.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RasterRule {
+    /// The centre of the cell is on land.
+    Centre,
+    /// Any part of the cell is on land.
+    AnyLand,
+    /// All of the cell is on land.
+    AllLand,
+    /// More than half of the cell is on land.
+    Majority,
+    /// The fraction of the cell that is on land.
+    Fraction,
+}
@@ -0,0 +1,237 @@
+//! Reading the rings of the land polygons from ESRI shapefiles (the `.shp` file), e.g. the OSM
+//! `land-polygons-complete-4326` or the GSHHG `GSHHS_f_L1` shapefiles.
+//!
+//! Only the polygons are read, other shapes are skipped like for WKB, and only the horizontal
+//! coordinates are kept. The attributes in the `.dbf` file are not read.
+use std::convert::TryInto;
+use std::io;
+
+use crate::coast::{ring_contains, signed_area};
+
+const FILE_CODE: i32 = 9994;
+
+const NULL: i32 = 0;
+const POLYGON: i32 = 5;
+const POLYGON_Z: i32 = 15;
+const POLYGON_M: i32 = 25;
+
+/// Read the rings of all the polygons in the shapefile, along with whether the ring is an
+/// exterior ring (`true`) or an interior ring (`false`), like for `wkb::read_rings`.
+///
+/// Exterior rings are clockwise in shapefiles, and holes counter-clockwise. The holes of a shape
+/// follow the innermost exterior ring that contains them.
+pub fn read_rings(buf: &[u8]) -> io::Result<Vec<(Vec<[f64; 2]>, bool)>> {
+    if buf.len() < 100 || be(buf, 0) != FILE_CODE {
+        return Err(invalid("not a shapefile"));
+    }
+
+    let mut rings = Vec::new();
+    let mut pos = 100;
+
+    while pos < buf.len() {
+        let len = buf
+            .get(pos..pos + 8)
+            .map(|h| 2 * be(h, 4) as usize)
+            .ok_or_else(|| invalid("unexpected end of shapefile record"))?;
+        pos += 8;
+
+        let record = buf
+            .get(pos..pos.saturating_add(len))
+            .ok_or_else(|| invalid("unexpected end of shapefile record"))?;
+        pos += len;
+
+        match record.get(..4).map(|t| le(t, 0)) {
+            Some(POLYGON | POLYGON_Z | POLYGON_M) => polygon(record, &mut rings)?,
+            Some(NULL) => {}
+            // Points and lines.
+            Some(_) => {}
+            None => return Err(invalid("empty shapefile record")),
+        }
+    }
+
+    Ok(rings)
+}
+
+/// Read the rings of a polygon record, and put the holes after the innermost exterior rings
+/// containing them.
+fn polygon(record: &[u8], rings: &mut Vec<(Vec<[f64; 2]>, bool)>) -> io::Result<()> {
+    let field = |i: usize| {
+        record
+            .get(i..i + 4)
+            .map(|b| le(b, 0))
+            .filter(|v| *v >= 0)
+            .map(|v| v as usize)
+            .ok_or_else(|| invalid("invalid shapefile polygon"))
+    };
+
+    // The shape type and bounding box come first.
+    let nparts = field(36)?;
+    let npoints = field(40)?;
+    let points = 44usize.saturating_add(4usize.saturating_mul(nparts));
+
+    if points.saturating_add(16usize.saturating_mul(npoints)) > record.len() {
+        return Err(invalid("unexpected end of shapefile polygon"));
+    }
+
+    let mut parts = (0..nparts)
+        .map(|i| field(44 + 4 * i))
+        .collect::<io::Result<Vec<_>>>()?;
+    parts.push(npoints);
+
+    let mut exteriors = Vec::new();
+    let mut holes = Vec::new();
+
+    for p in parts.windows(2) {
+        if p[0] > p[1] || p[1] > npoints {
+            return Err(invalid("invalid shapefile polygon parts"));
+        }
+
+        let ring: Vec<[f64; 2]> = (p[0]..p[1])
+            .map(|i| {
+                let b = &record[points + 16 * i..points + 16 * i + 16];
+                [
+                    f64::from_le_bytes(b[..8].try_into().unwrap()),
+                    f64::from_le_bytes(b[8..].try_into().unwrap()),
+                ]
+            })
+            .collect();
+
+        if signed_area(&ring) < 0. {
+            exteriors.push(ring);
+        } else {
+            holes.push(ring);
+        }
+    }
+
+    let start = rings.len();
+    rings.extend(exteriors.into_iter().map(|r| (r, true)));
+
+    for hole in holes {
+        // The innermost exterior ring containing the hole, e.g. an island in a lake may contain
+        // a pond.
+        let at = (start..rings.len())
+            .filter(|i| {
+                rings[*i].1
+                    && hole
+                        .first()
+                        .is_some_and(|p| ring_contains(&rings[*i].0, *p))
+            })
+            .map(|i| (i, signed_area(&rings[i].0).abs()))
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(i, _)| i)
+            .ok_or_else(|| invalid("hole outside the exterior rings in shapefile polygon"))?;
+
+        // After the exterior ring and the holes already there.
+        let at = (at + 1..rings.len())
+            .find(|i| rings[*i].1)
+            .unwrap_or(rings.len());
+        rings.insert(at, (hole, false));
+    }
+
+    Ok(())
+}
+
+fn be(b: &[u8], i: usize) -> i32 {
+    i32::from_be_bytes(b[i..i + 4].try_into().unwrap())
+}
+
+fn le(b: &[u8], i: usize) -> i32 {
+    i32::from_le_bytes(b[i..i + 4].try_into().unwrap())
+}
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A shapefile with the records, given as their shape type and content after it.
+    fn shapefile(records: &[(i32, Vec<u8>)]) -> Vec<u8> {
+        let mut buf = vec![0; 100];
+        buf[..4].copy_from_slice(&FILE_CODE.to_be_bytes());
+        buf[28..32].copy_from_slice(&1000i32.to_le_bytes());
+        buf[32..36].copy_from_slice(&POLYGON.to_le_bytes());
+
+        for (i, (ty, content)) in records.iter().enumerate() {
+            buf.extend_from_slice(&(i as i32 + 1).to_be_bytes());
+            buf.extend_from_slice(&((content.len() as i32 + 4) / 2).to_be_bytes());
+            buf.extend_from_slice(&ty.to_le_bytes());
+            buf.extend_from_slice(content);
+        }
+
+        let len = buf.len() as i32 / 2;
+        buf[24..28].copy_from_slice(&len.to_be_bytes());
+        buf
+    }
+
+    fn polygon(rings: &[&[[f64; 2]]]) -> Vec<u8> {
+        let mut buf = vec![0; 32];
+        buf.extend_from_slice(&(rings.len() as i32).to_le_bytes());
+        let npoints: usize = rings.iter().map(|r| r.len()).sum();
+        buf.extend_from_slice(&(npoints as i32).to_le_bytes());
+
+        let mut start = 0;
+        for r in rings {
+            buf.extend_from_slice(&(start as i32).to_le_bytes());
+            start += r.len();
+        }
+        for p in rings.iter().flat_map(|r| r.iter()) {
+            buf.extend_from_slice(&p[0].to_le_bytes());
+            buf.extend_from_slice(&p[1].to_le_bytes());
+        }
+
+        buf
+    }
+
+    #[test]
+    fn test_polygons() {
+        let a: &[[f64; 2]] = &[[0., 0.], [0., 2.], [2., 2.], [2., 0.], [0., 0.]];
+        let a_hole: &[[f64; 2]] = &[[0.5, 0.5], [1.5, 0.5], [1.5, 1.5], [0.5, 0.5]];
+        let b: &[[f64; 2]] = &[[5., 5.], [5., 6.], [6., 6.], [5., 5.]];
+
+        // The hole comes first, and belongs to the second exterior ring.
+        let buf = shapefile(&[
+            (POLYGON, polygon(&[a_hole, b, a])),
+            (NULL, vec![]),
+            (POLYGON, polygon(&[b])),
+        ]);
+
+        assert_eq!(
+            read_rings(&buf).unwrap(),
+            vec![
+                (b.to_vec(), true),
+                (a.to_vec(), true),
+                (a_hole.to_vec(), false),
+                (b.to_vec(), true),
+            ]
+        );
+
+        assert!(read_rings(&buf[..buf.len() - 1]).is_err());
+        assert!(read_rings(&buf[..50]).is_err());
+        assert!(read_rings(b"not a shapefile").is_err());
+    }
+
+    #[test]
+    fn test_nested_rings() {
+        // A lake with an island, which has a pond.
+        let land: &[[f64; 2]] = &[[0., 0.], [0., 10.], [10., 10.], [10., 0.], [0., 0.]];
+        let lake: &[[f64; 2]] = &[[1., 1.], [9., 1.], [9., 9.], [1., 9.], [1., 1.]];
+        let island: &[[f64; 2]] = &[[2., 2.], [2., 8.], [8., 8.], [8., 2.], [2., 2.]];
+        let pond: &[[f64; 2]] = &[[3., 3.], [7., 3.], [7., 7.], [3., 7.], [3., 3.]];
+
+        // The pond is inside both the land and the island, and belongs to the island.
+        let buf = shapefile(&[(POLYGON, polygon(&[pond, land, lake, island]))]);
+
+        assert_eq!(
+            read_rings(&buf).unwrap(),
+            vec![
+                (land.to_vec(), true),
+                (lake.to_vec(), false),
+                (island.to_vec(), true),
+                (pond.to_vec(), false),
+            ]
+        );
+    }
+}
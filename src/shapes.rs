@@ -1,96 +1,109 @@
+#[cfg(feature = "python")]
 use pyo3::{prelude::*, types::PyBytes};
 use std::borrow::Borrow;
 use std::fs::File;
 use std::io::{self, prelude::*};
 use std::path::Path;
 
-use geos::{CoordSeq, Geom, Geometry, PreparedGeometry};
-use numpy::{PyArray, PyReadonlyArrayDyn};
-
+#[cfg(feature = "geos")]
+use geos::{Geom, Geometry};
+#[cfg(feature = "python")]
+use numpy::{PyArray, PyArrayDyn};
+
+#[cfg(feature = "python")]
+use crate::batch::PyCoords;
+use crate::coast::{Coastline, Nearest};
+#[cfg(feature = "python")]
+use crate::error::InvalidPolicy;
+use crate::error::{check_coordinates, LandmaskError};
+use crate::longitude::LongitudeConvention;
 pub use crate::providers::LandmaskProvider;
 
-#[pyclass]
+/// The land shapes.
+///
+/// The shapes are kept as the coastline, and points are checked against it. With the `geos`
+/// feature the WKB is read with GEOS, otherwise with the pure-Rust WKB reader.
+#[cfg_attr(feature = "python", pyclass)]
+#[derive(Clone)]
 pub struct Shapes {
-    // prepped requires `geom` above to be around, and is valid as long as geom is alive.
-    geom: *mut Geometry,
-    prepped: PreparedGeometry<'static>,
+    coast: Coastline,
+    /// The range of the longitudes given to the point queries.
+    longitudes: LongitudeConvention,
 }
 
-impl Drop for Shapes {
-    fn drop(&mut self) {
-        unsafe { drop(Box::from_raw(self.geom)) }
-    }
-}
+#[cfg(feature = "geos")]
+impl Shapes {
+    /// Make the shapes from the polygons of the geometry, the geometry is not kept.
+    pub fn from_geom(geom: Geometry) -> Result<Shapes, LandmaskError> {
+        let mut rings = Vec::new();
+        collect_rings(&geom, &mut rings)?;
 
-// PreparedGeometry is Send+Sync, Geometry is Send+Sync. *mut Geometry is never modified.
-unsafe impl Send for Shapes {}
-unsafe impl Sync for Shapes {}
+        Shapes::from_rings(rings)
+    }
 
-// `PreparededGeometry::contains` needs a call to `contains` before it is thread-safe:
-// https://github.com/georust/geos/issues/95
-fn warmup_prepped(prepped: &PreparedGeometry) {
-    let point = CoordSeq::new_from_vec(&[&[0.0, 0.0]]).unwrap();
-    let point = Geometry::create_point(point).unwrap();
-    prepped.contains(&point).unwrap();
-}
+    /// Read the shapes from WKB.
+    pub fn from_wkb(buf: &[u8]) -> Result<Shapes, LandmaskError> {
+        let g = geos::Geometry::new_from_wkb(buf)
+            .map_err(|_| LandmaskError::InvalidData("cannot read WKB".into()))?;
+        Shapes::from_geom(g)
+    }
 
-impl Clone for Shapes {
-    fn clone(&self) -> Self {
-        let geom = unsafe { Clone::clone(&*self.geom) };
-        // let geom = Clone::clone(&geom);
+    pub fn get_geometry_from_compressed<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Geometry, LandmaskError> {
+        let buf = read_compressed(path)?;
 
-        Shapes::from_geom(geom).unwrap()
+        geos::Geometry::new_from_wkb(&buf)
+            .map_err(|_| LandmaskError::InvalidData("cannot read WKB".into()))
     }
 }
 
+#[cfg(not(feature = "geos"))]
 impl Shapes {
-    pub fn from_geom(geom: Geometry) -> io::Result<Shapes> {
-        let bxd = Box::new(geom);
-        let gptr = Box::into_raw(bxd);
-        let prepped = unsafe { (&*gptr).to_prepared_geom() }
-            .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "cannot prepare geomtry"))?;
-
-        // let prepped = geom
-        //     .to_prepared_geom()
-        //     .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "cannot prepare geomtry"))?;
-        warmup_prepped(&prepped);
+    /// Read the shapes from WKB.
+    pub fn from_wkb(buf: &[u8]) -> Result<Shapes, LandmaskError> {
+        Shapes::from_rings(crate::wkb::read_rings(buf)?)
+    }
+}
 
+impl Shapes {
+    /// Make the shapes from the rings of the polygons, see `rings`. The interior rings of a
+    /// polygon must follow its exterior ring.
+    pub fn from_rings(rings: Vec<(Vec<[f64; 2]>, bool)>) -> Result<Shapes, LandmaskError> {
         Ok(Shapes {
-            geom: gptr,
-            prepped,
+            coast: Coastline::from_rings(rings),
+            longitudes: LongitudeConvention::Any,
         })
     }
 
-    pub fn from_compressed<P: AsRef<Path>>(path: P) -> io::Result<Shapes> {
-        let g = Shapes::get_geometry_from_compressed(path)?;
-
-        Shapes::from_geom(g)
+    /// The rings of all the polygons in the shapes, along with whether the ring is an exterior
+    /// ring (`true`) or an interior ring (`false`).
+    #[allow(clippy::type_complexity)]
+    pub fn rings(&self) -> Result<Vec<(Vec<[f64; 2]>, bool)>, LandmaskError> {
+        Ok(self.coastline().rings())
     }
 
-    pub fn get_geometry_from_compressed<P: AsRef<Path>>(path: P) -> io::Result<Geometry> {
-        let fd = File::open(path)?;
-        let fd = io::BufReader::new(fd);
-        let mut fd = xz2::bufread::XzDecoder::new(fd);
-        let mut buf = Vec::new();
-        fd.read_to_end(&mut buf)?;
+    /// The coastline of the shapes, indexed for local queries.
+    pub fn coastline(&self) -> &Coastline {
+        &self.coast
+    }
 
-        Ok(geos::Geometry::new_from_wkb(&buf).unwrap())
+    /// Check if the segment from (x0, y0) to (x1, y1) touches the shapes, the segment must
+    /// be within [-180, 180].
+    fn intersects_line(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        self.contains_unchecked(x0, y0)
+            || self.contains_unchecked(x1, y1)
+            || self.coastline().first_crossing(x0, y0, x1, y1).is_some()
     }
-}
 
-#[pymethods]
-impl Shapes {
-    /// Make a new Gshhg shapes instance.
-    #[staticmethod]
-    pub fn new(py: Python, provider: LandmaskProvider) -> io::Result<Self> {
-        let buf = Shapes::wkb(py, provider)?;
-        let g = geos::Geometry::new_from_wkb(buf.as_bytes()).unwrap();
-        Shapes::from_geom(g)
+    /// Make new shapes from the embedded shapes of the provider.
+    pub fn new(provider: LandmaskProvider) -> Result<Self, LandmaskError> {
+        let buf = Shapes::wkb(provider)?;
+        Shapes::from_wkb(&buf)
     }
 
-    /// Get the WKB for the GSHHG shapes (full resolution).
-    #[staticmethod]
-    pub fn wkb(py: Python, provider: LandmaskProvider) -> io::Result<&PyBytes> {
+    /// Get the WKB for the embedded shapes of the provider (full resolution).
+    pub fn wkb(provider: LandmaskProvider) -> Result<Vec<u8>, LandmaskError> {
         use crate::GsshgData;
         use crate::OsmData;
 
@@ -98,7 +111,7 @@ impl Shapes {
             LandmaskProvider::Gshhg => GsshgData::get("gshhg.wkb.xz"),
             LandmaskProvider::Osm => OsmData::get("osm.wkb.xz"),
         }
-        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cannot find shapes"))?;
+        .ok_or_else(|| LandmaskError::InvalidData("cannot find shapes".into()))?;
 
         let buf: &[u8] = buf.data.borrow();
         let mut fd = xz2::read::XzDecoder::new(buf);
@@ -106,7 +119,7 @@ impl Shapes {
         let mut buf = Vec::new();
         fd.read_to_end(&mut buf)?;
 
-        Ok(PyBytes::new(py, &buf))
+        Ok(buf)
     }
 
     /// Check if point (x, y) is on land.
@@ -114,54 +127,292 @@ impl Shapes {
     /// `x` is longitude, [-180, 180] east
     /// `y` is latitude,  [- 90,  90] north
     ///
+    /// The point is checked against the edges of the coastline close to it, see
+    /// `Coastline::contains`.
+    ///
     /// Returns `true` if the point is on land.
     pub fn contains(&self, x: f64, y: f64) -> bool {
-        let x = super::modulate_longitude(x);
+        let x = self.longitude(x);
         debug_assert!(x >= -180. && x <= 180.);
         assert!(y > -90. && y <= 90.);
 
-        let point = CoordSeq::new_from_vec(&[&[x as f64, y as f64]]).unwrap();
-        let point = Geometry::create_point(point).unwrap();
-        self.prepped.contains(&point).unwrap()
+        self.contains_unchecked(x, y)
+    }
+
+    /// Same as `contains`, but returns an error for NaN or infinite coordinates, latitudes
+    /// outside [-90, 90] and longitudes outside the longitude convention instead of panicking.
+    pub fn try_contains(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        check_coordinates(x, y)?;
+
+        Ok(self.contains_unchecked(self.longitudes.normalize(x)?, y))
+    }
+
+    /// Use the longitude convention for the point and segment queries, see
+    /// `RoaringLandmask::with_longitude_convention`.
+    pub fn with_longitude_convention(mut self, longitudes: LongitudeConvention) -> Self {
+        self.longitudes = longitudes;
+        self
+    }
+
+    /// The longitude convention of the point and segment queries.
+    pub fn longitude_convention(&self) -> LongitudeConvention {
+        self.longitudes
+    }
+
+    /// The longitude `x` wrapped onto [-180, 180), panicking if it is outside the convention.
+    fn longitude(&self, x: f64) -> f64 {
+        self.longitudes
+            .normalize(x)
+            .unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Check if the segment from (x0, y0) to (x1, y1) touches land.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// The segment goes the shortest way around the Earth, so a segment from 179 to -179 crosses
+    /// the antimeridian.
+    pub fn intersects_segment(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        assert!(y0 > -90. && y0 <= 90.);
+        assert!(y1 > -90. && y1 <= 90.);
+
+        let x0 = self.longitude(x0);
+        let x1 = x0 + (self.longitude(x1) - x0 + 180.).rem_euclid(360.) - 180.;
+
+        self.intersects_segment_unchecked(x0, y0, x1, y1)
+    }
+
+    /// Same as `intersects_segment`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_intersects_segment(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<bool, LandmaskError> {
+        check_coordinates(x0, y0)?;
+        check_coordinates(x1, y1)?;
+
+        let x0 = self.longitudes.normalize(x0)?;
+        let x1 = x0 + (self.longitudes.normalize(x1)? - x0 + 180.).rem_euclid(360.) - 180.;
+
+        Ok(self.intersects_segment_unchecked(x0, y0, x1, y1))
+    }
+
+    /// Find the point on the coastline closest to point (x, y), along with the bearing to it
+    /// and the normal of the coastline pointing out of land.
+    ///
+    /// `x` is longitude, [-180, 180] east
+    /// `y` is latitude,  [- 90,  90] north
+    ///
+    /// Returns `None` if there is no coastline.
+    pub fn nearest_coast(&self, x: f64, y: f64) -> Option<Nearest> {
+        assert!(y >= -90. && y <= 90.);
+
+        let x = self.longitude(x);
+        self.coastline().nearest(x, y)
+    }
+
+    /// Same as `nearest_coast`, but returns an error for invalid coordinates instead of
+    /// panicking, see `try_contains`.
+    pub fn try_nearest_coast(&self, x: f64, y: f64) -> Result<Option<Nearest>, LandmaskError> {
+        check_coordinates(x, y)?;
+
+        Ok(self.coastline().nearest(self.longitudes.normalize(x)?, y))
+    }
+
+    pub fn from_compressed<P: AsRef<Path>>(path: P) -> Result<Shapes, LandmaskError> {
+        let buf = read_compressed(path)?;
+
+        Shapes::from_wkb(&buf)
+    }
+}
+
+fn read_compressed<P: AsRef<Path>>(path: P) -> io::Result<Vec<u8>> {
+    let fd = File::open(path)?;
+    let fd = io::BufReader::new(fd);
+    let mut fd = xz2::bufread::XzDecoder::new(fd);
+    let mut buf = Vec::new();
+    fd.read_to_end(&mut buf)?;
+
+    Ok(buf)
+}
+
+/// Make a GEOS multipolygon from the rings of the polygons, e.g. from the GSHHG or shapefile
+/// readers, for `Shapes::from_geom`. The interior rings of a polygon must follow its exterior
+/// ring.
+#[cfg(feature = "geos")]
+pub fn geometry_from_rings(rings: &[(Vec<[f64; 2]>, bool)]) -> Result<Geometry, LandmaskError> {
+    geos::Geometry::new_from_wkb(&crate::wkb::write_rings(rings))
+        .map_err(|_| LandmaskError::InvalidData("invalid rings".into()))
+}
+
+#[cfg(feature = "geos")]
+pub(crate) fn collect_rings<G: Geom>(
+    g: &G,
+    rings: &mut Vec<(Vec<[f64; 2]>, bool)>,
+) -> geos::GResult<()> {
+    use geos::GeometryTypes;
+
+    match g.geometry_type() {
+        GeometryTypes::Polygon => {
+            rings.push((ring_coords(&g.get_exterior_ring()?)?, true));
+
+            for i in 0..g.get_num_interior_rings()? {
+                rings.push((ring_coords(&g.get_interior_ring_n(i as u32)?)?, false));
+            }
+        }
+        GeometryTypes::MultiPolygon | GeometryTypes::GeometryCollection => {
+            for i in 0..g.get_num_geometries()? {
+                collect_rings(&g.get_geometry_n(i)?, rings)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "geos")]
+fn ring_coords<G: Geom>(ring: &G) -> geos::GResult<Vec<[f64; 2]>> {
+    let cs = ring.get_coord_seq()?;
+
+    (0..cs.size()?)
+        .map(|i| Ok([cs.get_x(i)?, cs.get_y(i)?]))
+        .collect()
+}
+
+#[cfg_attr(feature = "python", pymethods)]
+impl Shapes {
+    /// Make a new Gshhg shapes instance.
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "new")]
+    fn py_new(provider: LandmaskProvider) -> Result<Self, LandmaskError> {
+        Shapes::new(provider)
+    }
+
+    /// Get the WKB for the GSHHG shapes (full resolution).
+    #[cfg(feature = "python")]
+    #[staticmethod]
+    #[pyo3(name = "wkb")]
+    fn py_wkb(py: Python, provider: LandmaskProvider) -> Result<&PyBytes, LandmaskError> {
+        Ok(PyBytes::new(py, &Shapes::wkb(provider)?))
+    }
+
+    #[cfg(feature = "python")]
+    #[getter]
+    #[pyo3(name = "longitude_convention")]
+    fn py_longitude_convention(&self) -> LongitudeConvention {
+        self.longitudes
+    }
+
+    #[cfg(feature = "python")]
+    #[setter]
+    #[pyo3(name = "longitude_convention")]
+    fn py_set_longitude_convention(&mut self, longitudes: LongitudeConvention) {
+        self.longitudes = longitudes;
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "contains")]
+    fn py_contains(&self, x: f64, y: f64) -> Result<bool, LandmaskError> {
+        self.try_contains(x, y)
     }
 
     /// Same as `contains`, but does not check for bounds.
     pub(crate) fn contains_unchecked(&self, x: f64, y: f64) -> bool {
-        let point = CoordSeq::new_from_vec(&[&[x, y]]).unwrap();
-        let point = Geometry::create_point(point).unwrap();
-        self.prepped.contains(&point).unwrap()
+        self.coastline().contains(x, y)
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "intersects_segment")]
+    fn py_intersects_segment(
+        &self,
+        x0: f64,
+        y0: f64,
+        x1: f64,
+        y1: f64,
+    ) -> Result<bool, LandmaskError> {
+        self.try_intersects_segment(x0, y0, x1, y1)
+    }
+
+    /// Same as `intersects_segment`, but does not check for bounds or wrap the segment. `x1` may
+    /// be outside [-180, 180] for segments crossing the antimeridian.
+    pub(crate) fn intersects_segment_unchecked(&self, x0: f64, y0: f64, x1: f64, y1: f64) -> bool {
+        if x1 > 180. || x1 < -180. {
+            // Split the segment where it crosses the antimeridian.
+            let xc = 180f64.copysign(x1);
+            let yc = y0 + (y1 - y0) * (xc - x0) / (x1 - x0);
+
+            return self.intersects_segment_unchecked(x0, y0, xc, yc)
+                || self.intersects_segment_unchecked(-xc, yc, x1 - 2. * xc, y1);
+        }
+
+        if x0 == x1 && y0 == y1 {
+            return self.contains_unchecked(x0, y0);
+        }
+
+        self.intersects_line(x0, y0, x1, y1)
+    }
+
+    #[cfg(feature = "python")]
+    #[pyo3(name = "nearest_coast")]
+    fn py_nearest_coast(&self, x: f64, y: f64) -> Result<Option<Nearest>, LandmaskError> {
+        self.try_nearest_coast(x, y)
     }
 
+    /// Check many points, see `contains`. Invalid points raise an error, or are treated as ocean
+    /// or land, depending on `invalid`.
+    ///
+    /// The coordinates may be float64, float32, int64 or int32 arrays, and are multiplied by
+    /// `scale`, e.g. `1e-7` for integers in units of 1e-7 degrees.
+    ///
+    /// The results are written to `out` if it is given, which must have the same size as `x`.
+    /// The GIL is released while the points are checked.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, invalid = InvalidPolicy::Raise, scale = 1., out = None))]
     pub fn contains_many(
         &self,
         py: Python,
-        x: PyReadonlyArrayDyn<f64>,
-        y: PyReadonlyArrayDyn<f64>,
-    ) -> Py<PyArray<bool, numpy::Ix1>> {
-        let x = x.as_array();
-        let y = y.as_array();
+        x: PyCoords,
+        y: PyCoords,
+        invalid: InvalidPolicy,
+        scale: f64,
+        out: Option<&PyArrayDyn<bool>>,
+    ) -> Result<PyObject, LandmaskError> {
+        crate::batch::contains_many_py(self, py, x, y, out, scale, invalid)
+    }
 
-        PyArray::from_iter(
-            py,
-            x.iter().zip(y.iter()).map(|(x, y)| self.contains(*x, *y)),
-        )
-        .to_owned()
+    /// Check many points, giving `1` for land, `0` for ocean and `-1` for invalid points, e.g. NaN
+    /// or latitudes outside [-90, 90]. The result has the same shape as `x` and `y`.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, scale = 1.))]
+    pub fn contains_many_i8(
+        &self,
+        py: Python,
+        x: PyCoords,
+        y: PyCoords,
+        scale: f64,
+    ) -> Result<Py<PyArray<i8, numpy::IxDyn>>, LandmaskError> {
+        crate::batch::contains_many_i8_py(self, py, x, y, scale)
     }
 
+    /// Check many points in parallel, see `contains_many`.
+    #[cfg(feature = "python")]
+    #[pyo3(signature = (x, y, invalid = InvalidPolicy::Raise, scale = 1., out = None))]
     pub fn contains_many_par(
         &self,
         py: Python,
-        x: PyReadonlyArrayDyn<f64>,
-        y: PyReadonlyArrayDyn<f64>,
-    ) -> Py<PyArray<bool, numpy::IxDyn>> {
-        let x = x.as_array();
-        let y = y.as_array();
-
-        use ndarray::Zip;
-        let contains = Zip::from(&x)
-            .and(&y)
-            .par_map_collect(|x, y| self.contains(*x, *y));
-        PyArray::from_owned_array(py, contains).to_owned()
+        x: PyCoords,
+        y: PyCoords,
+        invalid: InvalidPolicy,
+        scale: f64,
+        out: Option<&PyArrayDyn<bool>>,
+    ) -> Result<PyObject, LandmaskError> {
+        crate::batch::contains_many_par_py(self, py, x, y, out, scale, invalid)
     }
 }
 
@@ -169,6 +420,23 @@ impl Shapes {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_longitude_convention() {
+        let rings = vec![(
+            vec![[170., 0.], [180., 0.], [180., 1.], [170., 1.], [170., 0.]],
+            true,
+        )];
+        let s = Shapes::from_rings(rings)
+            .unwrap()
+            .with_longitude_convention(LongitudeConvention::Positive);
+
+        assert!(s.try_contains(175., 0.5).unwrap());
+        assert!(s.try_contains(-175., 0.5).is_err());
+        assert!(s.try_intersects_segment(160., 0.5, 190., 0.5).unwrap());
+        assert!(s.try_nearest_coast(-165., 0.5).is_err());
+        assert!(s.clone().try_contains(-175., 0.5).is_err());
+    }
+
     #[test]
     fn test_load_compressed() {
         let _s = Shapes::from_compressed("assets/gshhg.wkb.xz").unwrap();
@@ -177,35 +445,86 @@ mod tests {
 
     #[test]
     fn test_load() {
-        pyo3::prepare_freethreaded_python();
-        Python::with_gil(|py| {
-            Shapes::new(py, LandmaskProvider::Gshhg).unwrap();
-            Shapes::new(py, LandmaskProvider::Osm).unwrap();
-        });
+        Shapes::new(LandmaskProvider::Gshhg).unwrap();
+        Shapes::new(LandmaskProvider::Osm).unwrap();
     }
 
     #[test]
     fn test_np() {
-        pyo3::prepare_freethreaded_python();
-        Python::with_gil(|py| {
-            let mask = Shapes::new(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(!mask.contains(5., 90.));
+        let mask = Shapes::new(LandmaskProvider::Gshhg).unwrap();
+        assert!(!mask.contains(5., 90.));
 
-            let mask = Shapes::new(py, LandmaskProvider::Osm).unwrap();
-            assert!(!mask.contains(5., 90.));
-        });
+        let mask = Shapes::new(LandmaskProvider::Osm).unwrap();
+        assert!(!mask.contains(5., 90.));
     }
 
     #[test]
     fn test_sp() {
-        pyo3::prepare_freethreaded_python();
-        Python::with_gil(|py| {
-            let mask = Shapes::new(py, LandmaskProvider::Gshhg).unwrap();
-            assert!(mask.contains(5., -89.99));
+        let mask = Shapes::new(LandmaskProvider::Gshhg).unwrap();
+        assert!(mask.contains(5., -89.99));
+
+        let mask = Shapes::new(LandmaskProvider::Osm).unwrap();
+        assert!(mask.contains(5., -89.99));
+    }
+
+    #[test]
+    fn test_intersects_segment() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let s = Shapes::new(provider).unwrap();
+
+            // Across southern Norway, along the Norwegian sea, and ending on land.
+            assert!(s.intersects_segment(5., 60., 12., 60.));
+            assert!(!s.intersects_segment(0., 66., 2., 68.));
+            assert!(s.intersects_segment(5., 65.6, 15., 65.6));
+
+            // Across the antimeridian through Fiji.
+            assert!(s.intersects_segment(178., -16.5, -179.5, -16.5));
+        }
+    }
+
+    #[cfg(feature = "geos")]
+    #[test]
+    fn test_wkb_rings_match_geos() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let wkb = Shapes::wkb(provider).unwrap();
+            let geom = Geometry::new_from_wkb(&wkb).unwrap();
+
+            let mut rings = Vec::new();
+            collect_rings(&geom, &mut rings).unwrap();
 
-            let mask = Shapes::new(py, LandmaskProvider::Osm).unwrap();
-            assert!(mask.contains(5., -89.99));
-        });
+            assert_eq!(crate::wkb::read_rings(&wkb).unwrap(), rings);
+        }
+    }
+
+    #[cfg(feature = "geos")]
+    #[test]
+    fn test_coastline_matches_geos() {
+        for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+            let s = Shapes::new(provider).unwrap();
+            let geom = Geometry::new_from_wkb(&Shapes::wkb(provider).unwrap()).unwrap();
+            let prepped = geom.to_prepared_geom().unwrap();
+
+            // Southern Norway, and Fiji across the antimeridian.
+            for (x0, y0) in [(4., 58.), (177., -19.)] {
+                for i in 0..100 {
+                    for j in 0..100 {
+                        let (x, y) = (x0 + i as f64 * 0.0397, y0 + j as f64 * 0.0397);
+                        let x = crate::modulate_longitude(x);
+
+                        let point = geos::CoordSeq::new_from_vec(&[&[x, y]]).unwrap();
+                        let point = Geometry::create_point(point).unwrap();
+
+                        assert_eq!(
+                            s.contains(x, y),
+                            prepped.contains(&point).unwrap(),
+                            "{} {}",
+                            x,
+                            y
+                        );
+                    }
+                }
+            }
+        }
     }
 
     #[cfg(feature = "nightly")]
@@ -215,27 +534,21 @@ mod tests {
 
         #[bench]
         fn test_contains_on_land(b: &mut Bencher) {
-            pyo3::prepare_freethreaded_python();
-            Python::with_gil(|py| {
-                for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                    let s = Shapes::new(py, provider).unwrap();
-                    assert!(s.contains(15., 65.6));
-                    assert!(s.contains(10., 60.0));
-                    b.iter(|| s.contains(15., 65.6));
-                }
-            })
+            for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+                let s = Shapes::new(provider).unwrap();
+                assert!(s.contains(15., 65.6));
+                assert!(s.contains(10., 60.0));
+                b.iter(|| s.contains(15., 65.6));
+            }
         }
 
         #[bench]
         fn test_contains_in_ocean(b: &mut Bencher) {
-            pyo3::prepare_freethreaded_python();
-            Python::with_gil(|py| {
-                for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
-                    let s = Shapes::new(py, provider).unwrap();
-                    assert!(!s.contains(5., 65.6));
-                    b.iter(|| s.contains(5., 65.6));
-                }
-            })
+            for provider in [LandmaskProvider::Gshhg, LandmaskProvider::Osm] {
+                let s = Shapes::new(provider).unwrap();
+                assert!(!s.contains(5., 65.6));
+                b.iter(|| s.contains(5., 65.6));
+            }
         }
     }
 }
@@ -0,0 +1,200 @@
+//! Reading and writing the rings of the land polygons as well-known binary (WKB).
+//!
+//! Only the polygons are read, other geometries are skipped. Both ISO WKB and extended WKB (with
+//! SRID) are supported, with any number of dimensions, but only the horizontal coordinates are
+//! kept.
+use std::convert::TryInto;
+use std::io;
+
+/// Read the rings of all the polygons in the WKB geometry, along with whether the ring is an
+/// exterior ring (`true`) or an interior ring (`false`). The interior rings of a polygon follow
+/// its exterior ring.
+pub fn read_rings(buf: &[u8]) -> io::Result<Vec<(Vec<[f64; 2]>, bool)>> {
+    let mut reader = Reader { buf, pos: 0 };
+    let mut rings = Vec::new();
+    reader.geometry(&mut rings)?;
+
+    Ok(rings)
+}
+
+/// Write the rings as a little endian WKB multipolygon. Every exterior ring starts a new polygon,
+/// and is followed by its interior rings, like the rings from `read_rings`.
+pub fn write_rings(rings: &[(Vec<[f64; 2]>, bool)]) -> Vec<u8> {
+    let mut polygons: Vec<Vec<&[[f64; 2]]>> = Vec::new();
+    for (ring, exterior) in rings {
+        if *exterior || polygons.is_empty() {
+            polygons.push(Vec::new());
+        }
+        polygons.last_mut().unwrap().push(ring);
+    }
+
+    let mut buf = Vec::new();
+    buf.push(1);
+    buf.extend_from_slice(&MULTIPOLYGON.to_le_bytes());
+    buf.extend_from_slice(&(polygons.len() as u32).to_le_bytes());
+
+    for polygon in polygons {
+        buf.push(1);
+        buf.extend_from_slice(&POLYGON.to_le_bytes());
+        buf.extend_from_slice(&(polygon.len() as u32).to_le_bytes());
+
+        for ring in polygon {
+            buf.extend_from_slice(&(ring.len() as u32).to_le_bytes());
+            for p in ring {
+                buf.extend_from_slice(&p[0].to_le_bytes());
+                buf.extend_from_slice(&p[1].to_le_bytes());
+            }
+        }
+    }
+
+    buf
+}
+
+const POINT: u32 = 1;
+const LINESTRING: u32 = 2;
+const POLYGON: u32 = 3;
+const MULTIPOLYGON: u32 = 6;
+const GEOMETRYCOLLECTION: u32 = 7;
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> io::Result<&[u8]> {
+        let b = self
+            .buf
+            .get(self.pos..self.pos.saturating_add(n))
+            .ok_or_else(|| invalid("unexpected end of WKB"))?;
+        self.pos += n;
+
+        Ok(b)
+    }
+
+    fn u32(&mut self, le: bool) -> io::Result<u32> {
+        let b = self.take(4)?.try_into().unwrap();
+        Ok(if le {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+
+    fn f64(&mut self, le: bool) -> io::Result<f64> {
+        let b = self.take(8)?.try_into().unwrap();
+        Ok(if le {
+            f64::from_le_bytes(b)
+        } else {
+            f64::from_be_bytes(b)
+        })
+    }
+
+    fn geometry(&mut self, rings: &mut Vec<(Vec<[f64; 2]>, bool)>) -> io::Result<()> {
+        let le = match self.take(1)?[0] {
+            0 => false,
+            1 => true,
+            _ => return Err(invalid("invalid WKB byte order")),
+        };
+
+        let ty = self.u32(le)?;
+
+        // Extended WKB flags for Z, M and SRID.
+        let mut dims = 2 + (ty & 0x8000_0000 != 0) as usize + (ty & 0x4000_0000 != 0) as usize;
+        if ty & 0x2000_0000 != 0 {
+            self.u32(le)?;
+        }
+
+        // ISO WKB types for Z, M and ZM.
+        let ty = ty & 0x0fff_ffff;
+        dims += match ty / 1000 {
+            0 => 0,
+            1 | 2 => 1,
+            3 => 2,
+            _ => return Err(invalid("invalid WKB geometry type")),
+        };
+
+        match ty % 1000 {
+            POINT => {
+                self.take(8 * dims)?;
+            }
+            LINESTRING => {
+                self.points(le, dims)?;
+            }
+            POLYGON => {
+                for i in 0..self.u32(le)? {
+                    let ring = self.points(le, dims)?;
+                    rings.push((ring, i == 0));
+                }
+            }
+            4..=GEOMETRYCOLLECTION => {
+                for _ in 0..self.u32(le)? {
+                    self.geometry(rings)?;
+                }
+            }
+            _ => return Err(invalid("unsupported WKB geometry type")),
+        }
+
+        Ok(())
+    }
+
+    fn points(&mut self, le: bool, dims: usize) -> io::Result<Vec<[f64; 2]>> {
+        let n = self.u32(le)? as usize;
+        if n.saturating_mul(8 * dims) > self.buf.len() - self.pos {
+            return Err(invalid("unexpected end of WKB"));
+        }
+
+        let mut points = Vec::with_capacity(n);
+        for _ in 0..n {
+            let x = self.f64(le)?;
+            let y = self.f64(le)?;
+            self.take(8 * (dims - 2))?;
+            points.push([x, y]);
+        }
+
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_roundtrip() {
+        let rings = vec![
+            (vec![[0., 0.], [2., 0.], [2., 2.], [0., 2.], [0., 0.]], true),
+            (vec![[0.5, 0.5], [0.5, 1.5], [1.5, 1.5], [0.5, 0.5]], false),
+            (vec![[5., 5.], [6., 5.], [6., 6.], [5., 5.]], true),
+        ];
+
+        let buf = write_rings(&rings);
+        assert_eq!(read_rings(&buf).unwrap(), rings);
+
+        assert!(read_rings(&buf[..buf.len() - 1]).is_err());
+        assert!(read_rings(&[]).is_err());
+    }
+
+    #[test]
+    fn test_big_endian_polygon_z() {
+        // POLYGON Z ((0 0 1, 1 0 1, 0 1 1, 0 0 1)) as ISO WKB.
+        let mut buf = vec![0];
+        buf.extend_from_slice(&1003u32.to_be_bytes());
+        buf.extend_from_slice(&1u32.to_be_bytes());
+        buf.extend_from_slice(&4u32.to_be_bytes());
+        for p in [[0., 0.], [1., 0.], [0., 1.], [0., 0.]] {
+            buf.extend_from_slice(&f64::to_be_bytes(p[0]));
+            buf.extend_from_slice(&f64::to_be_bytes(p[1]));
+            buf.extend_from_slice(&1f64.to_be_bytes());
+        }
+
+        assert_eq!(
+            read_rings(&buf).unwrap(),
+            vec![(vec![[0., 0.], [1., 0.], [0., 1.], [0., 0.]], true)]
+        );
+    }
+}
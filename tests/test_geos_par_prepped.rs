@@ -1,3 +1,5 @@
+#![cfg(feature = "geos")]
+
 use geos::{CoordSeq, Geom, Geometry};
 use roaring_landmask::Shapes;
 
@@ -2,6 +2,12 @@ import pytest
 import numpy as np
 from roaring_landmask import RoaringLandmask
 from roaring_landmask import LandmaskProvider
+from roaring_landmask import CellClass
+from roaring_landmask import InvalidCoordinateError
+from roaring_landmask import InvalidPolicy
+from roaring_landmask import LongitudeConvention
+from roaring_landmask import RasterRule
+from roaring_landmask import Grid
 
 @pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
 def test_make_landmask(provider):
@@ -70,3 +76,177 @@ def test_landmask_many_par_few(benchmark):
   print ("points:", len(xx.ravel()))
   benchmark(l.contains_many_par, xx.ravel(), yy.ravel())
 
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_rasterize(provider):
+
+  l = RoaringLandmask.new_with_provider(provider)
+
+  centre = l.rasterize(4, 6, 8, 60, 62, 8, RasterRule.Centre)
+  assert centre.shape == (8, 8)
+  assert centre.dtype == bool
+
+  fraction = l.rasterize(4, 6, 8, 60, 62, 8, RasterRule.Fraction)
+  assert fraction.dtype == np.float64
+  assert np.all((fraction >= 0) & (fraction <= 1))
+
+  fiji = l.rasterize(179, -179, 4, -17, -16, 2, RasterRule.AnyLand)
+
+  l.longitude_convention = LongitudeConvention.Signed
+  np.testing.assert_array_equal(l.rasterize(179, -179, 4, -17, -16, 2, RasterRule.AnyLand), fiji)
+
+  with pytest.raises(InvalidCoordinateError):
+    l.rasterize(179, 181, 4, -17, -16, 2, RasterRule.AnyLand)
+
+  l.longitude_convention = LongitudeConvention.Positive
+  np.testing.assert_array_equal(l.rasterize(179, 181, 4, -17, -16, 2, RasterRule.AnyLand), fiji)
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_curvilinear_land_fraction(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+
+  lon, lat = np.meshgrid(np.linspace(4, 6, 9), np.linspace(60, 62, 9))
+  fraction = l.curvilinear_land_fraction(lon, lat)
+  assert fraction.shape == (8, 8)
+  assert np.all((fraction >= 0) & (fraction <= 1))
+
+  # Iceland, west of 0.
+  lon, lat = np.meshgrid(np.linspace(-20, -16, 5), np.linspace(64, 66, 5))
+  iceland = l.curvilinear_land_fraction(lon, lat)
+
+  l.longitude_convention = LongitudeConvention.Positive
+  np.testing.assert_array_equal(l.curvilinear_land_fraction(lon % 360, lat), iceland)
+
+  with pytest.raises(InvalidCoordinateError):
+    l.curvilinear_land_fraction(lon, lat)
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_classify(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+  assert l.classify(15., 65.6) == CellClass.Coastal
+
+  l = RoaringLandmask.new_with_provider(provider, interior_cells = True)
+  assert l.classify(15., 65.6) == CellClass.Land
+  assert l.classify(5., 65.6) == CellClass.Ocean
+
+  x = np.linspace(4, 12, 40)
+  y = np.linspace(58, 62, 25)
+  xx, yy = np.meshgrid(x, y)
+
+  c = l.classify_many(xx.ravel(), yy.ravel())
+  contains = l.contains_many(xx.ravel(), yy.ravel())
+  assert np.all(contains[c == int(CellClass.Land)])
+  assert not np.any(contains[c == int(CellClass.Ocean)])
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_invalid_coordinates(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+
+  with pytest.raises(InvalidCoordinateError):
+    l.contains(5., 95.)
+
+  with pytest.raises(InvalidCoordinateError):
+    l.contains(np.nan, 60.)
+
+  with pytest.raises(ValueError):
+    l.contains_many(np.array([5., 15.]), np.array([65.6, -95.]))
+
+  with pytest.raises(InvalidCoordinateError):
+    l.intersects_segment_many(np.array([4.]), np.array([60.]), np.array([11.5]), np.array([np.nan]))
+
+  with pytest.raises(InvalidCoordinateError):
+    l.distance_to_coast_many_par(np.array([5.]), np.array([-95.]))
+
+  with pytest.raises(ValueError):
+    l.rasterize(4, 6, 8, 62, 60, 8, RasterRule.Centre)
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_invalid_policy(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+
+  x = np.array([15., 5., np.nan, 5.])
+  y = np.array([65.6, 65.6, 60., -95.])
+
+  np.testing.assert_array_equal(l.contains_many(x, y, InvalidPolicy.Ocean), [True, False, False, False])
+  np.testing.assert_array_equal(l.contains_many_par(x, y, invalid=InvalidPolicy.Land), [True, False, True, True])
+  np.testing.assert_array_equal(l.contains_many_i8(x, y), [1, 0, -1, -1])
+  np.testing.assert_array_equal(l.mask.contains_many_i8(x, y), [1, 0, -1, -1])
+  np.testing.assert_array_equal(l.shapes.contains_many_i8(x, y), [1, 0, -1, -1])
+
+  with pytest.raises(InvalidCoordinateError):
+    l.contains_many_par(x, y)
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_longitude_convention(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+
+  assert l.contains(15. - 360., 65.6)
+  assert l.contains(15. + 720., 65.6)
+
+  l.longitude_convention = LongitudeConvention.Positive
+  assert l.contains(360. - 150., 65.)
+
+  with pytest.raises(InvalidCoordinateError):
+    l.contains(-150., 65.)
+
+  with pytest.raises(InvalidCoordinateError):
+    l.distance_to_coast(-150., 65.)
+
+  mask = l.mask
+  mask.longitude_convention = LongitudeConvention.Signed
+  assert mask.contains(-150., 65.)
+
+  with pytest.raises(InvalidCoordinateError):
+    mask.contains(210., 65.)
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_float32_and_int_coordinates(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+
+  x = np.array([15., 5.])
+  y = np.array([65.6, 65.6])
+  expected = l.contains_many(x, y)
+
+  np.testing.assert_array_equal(l.contains_many(x.astype(np.float32), y.astype(np.float32)), expected)
+  np.testing.assert_array_equal(l.contains_many_par(x.astype(np.float32), y.astype(np.float32)), expected)
+
+  xi = (x * 1e7).astype(np.int32)
+  yi = (y * 1e7).astype(np.int32)
+  np.testing.assert_array_equal(l.contains_many(xi, yi, scale=1e-7), expected)
+  np.testing.assert_array_equal(l.contains_many_i8(xi, yi, scale=1e-7), expected.astype(np.int8))
+
+  with pytest.raises(ValueError):
+    l.contains_many(x, y.astype(np.float32))
+
+@pytest.mark.parametrize("provider", [LandmaskProvider.Gshhg, LandmaskProvider.Osm])
+def test_out_array(provider):
+  l = RoaringLandmask.new_with_provider(provider)
+
+  x = np.array([15., 5.])
+  y = np.array([65.6, 65.6])
+  expected = l.contains_many(x, y)
+
+  out = np.zeros(len(x), dtype=bool)
+  assert l.contains_many(x, y, out=out) is out
+  np.testing.assert_array_equal(out, expected)
+
+  out = np.zeros(len(x), dtype=bool)
+  assert l.contains_many_par(x, y, out=out) is out
+  np.testing.assert_array_equal(out, expected)
+
+  with pytest.raises(ValueError):
+    l.contains_many(x, y, out=np.zeros(3, dtype=bool))
+
+def test_mask_grid():
+
+  mask = RoaringLandmask.new().mask
+  grid = mask.grid
+  assert (grid.nx, grid.ny) == (86400, 43200)
+  assert grid.wraps
+  assert grid.dx == mask.dx == 1. / 240.
+  assert mask.transform.sa == 240.
+  assert grid.extent == Grid.global_(1. / 240.).extent
+
+  grid = Grid.regional(5., 55., 30., 72., 0.25)
+  assert (grid.nx, grid.ny) == (100, 68)
+  assert not grid.wraps